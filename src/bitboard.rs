@@ -0,0 +1,244 @@
+use crate::{Colour, Piece, Position};
+
+// a set of tiles with one bit for each, a1 is bit 0, h1 bit 7 and h8 bit 63
+pub(crate) type Bitboard = u64;
+
+const RANK_4: Bitboard = 0xff << 24;
+const RANK_5: Bitboard = 0xff << 32;
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_attacks([
+  (1, 2),
+  (2, 1),
+  (2, -1),
+  (1, -2),
+  (-1, -2),
+  (-2, -1),
+  (-2, 1),
+  (-1, 2),
+]);
+const KING_ATTACKS: [Bitboard; 64] = leaper_attacks([
+  (0, 1),
+  (1, 1),
+  (1, 0),
+  (1, -1),
+  (0, -1),
+  (-1, -1),
+  (-1, 0),
+  (-1, 1),
+]);
+// the tiles a pawn attacks, white first
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [pawn_attacks_for(1), pawn_attacks_for(-1)];
+
+// as (row, col) steps, the first four go up the bits and the last four down
+const DIRECTIONS: [(i32, i32); 8] = [
+  (1, 0),
+  (0, 1),
+  (1, 1),
+  (1, -1),
+  (-1, 0),
+  (0, -1),
+  (-1, -1),
+  (-1, 1),
+];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+// every tile from a tile to the edge of the board in each direction
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+// the tile moved to by the step, if it is on the board
+const fn step(index: usize, row_step: i32, col_step: i32) -> Option<usize> {
+  let row = (index / 8) as i32 + row_step;
+  let col = (index % 8) as i32 + col_step;
+  if row < 0 || row > 7 || col < 0 || col > 7 {
+    return None;
+  }
+  Some((row * 8 + col) as usize)
+}
+
+const fn leaper_attacks(steps: [(i32, i32); 8]) -> [Bitboard; 64] {
+  let mut attacks = [0; 64];
+  let mut index = 0;
+  while index < 64 {
+    let mut i = 0;
+    while i < steps.len() {
+      if let Some(target) = step(index, steps[i].0, steps[i].1) {
+        attacks[index] |= 1 << target;
+      }
+      i += 1;
+    }
+    index += 1;
+  }
+  attacks
+}
+
+const fn pawn_attacks_for(forward: i32) -> [Bitboard; 64] {
+  let mut attacks = [0; 64];
+  let mut index = 0;
+  while index < 64 {
+    if let Some(target) = step(index, forward, -1) {
+      attacks[index] |= 1 << target;
+    }
+    if let Some(target) = step(index, forward, 1) {
+      attacks[index] |= 1 << target;
+    }
+    index += 1;
+  }
+  attacks
+}
+
+const fn rays() -> [[Bitboard; 64]; 8] {
+  let mut rays = [[0; 64]; 8];
+  let mut direction = 0;
+  while direction < 8 {
+    let (row_step, col_step) = DIRECTIONS[direction];
+    let mut index = 0;
+    while index < 64 {
+      let mut tile = step(index, row_step, col_step);
+      while let Some(target) = tile {
+        rays[direction][index] |= 1 << target;
+        tile = step(target, row_step, col_step);
+      }
+      index += 1;
+    }
+    direction += 1;
+  }
+  rays
+}
+
+// the rays stop at the first piece in the way, which can be captured
+fn slide(index: usize, occupied: Bitboard, directions: &[usize]) -> Bitboard {
+  let mut attacks = 0;
+  for direction in directions.iter() {
+    let ray = RAYS[*direction][index];
+    let blockers = ray & occupied;
+    attacks |= match (blockers, *direction < 4) {
+      (0, _) => ray,
+      (_, true) => ray ^ RAYS[*direction][blockers.trailing_zeros() as usize],
+      (_, false) => ray ^ RAYS[*direction][63 - blockers.leading_zeros() as usize],
+    };
+  }
+  attacks
+}
+
+pub(crate) fn index(position: &Position) -> usize {
+  position.0 * 8 + position.1
+}
+
+pub(crate) fn position(index: usize) -> Position {
+  Position(index / 8, index % 8)
+}
+
+pub(crate) fn tiles(bitboard: Bitboard) -> Tiles {
+  Tiles(bitboard)
+}
+
+// the tiles of a bitboard from the lowest bit up
+pub(crate) struct Tiles(Bitboard);
+
+impl Iterator for Tiles {
+  type Item = usize;
+
+  fn next(&mut self) -> Option<usize> {
+    if self.0 == 0 {
+      return None;
+    }
+    let index = self.0.trailing_zeros() as usize;
+    self.0 &= self.0 - 1;
+    Some(index)
+  }
+}
+
+// the tiles a pawn attacks, which are not the ones it moves to without capturing
+pub(crate) fn pawn_attacks(colour: Colour, index: usize) -> Bitboard {
+  PAWN_ATTACKS[side(colour)][index]
+}
+
+// the tiles any other piece attacks, with the pieces in the way given by occupied
+pub(crate) fn attacks(piece: Piece, index: usize, occupied: Bitboard) -> Bitboard {
+  match piece {
+    Piece::Pawn(colour) => pawn_attacks(colour, index),
+    Piece::Knight(_) => KNIGHT_ATTACKS[index],
+    Piece::King(_) => KING_ATTACKS[index],
+    Piece::Bishop(_) => slide(index, occupied, &BISHOP_DIRECTIONS),
+    Piece::Rook(_) => slide(index, occupied, &ROOK_DIRECTIONS),
+    Piece::Queen(_) => {
+      slide(index, occupied, &BISHOP_DIRECTIONS) | slide(index, occupied, &ROOK_DIRECTIONS)
+    }
+  }
+}
+
+// the tiles a pawn moves to without capturing
+pub(crate) fn pawn_pushes(colour: Colour, index: usize, occupied: Bitboard) -> Bitboard {
+  let pawn: Bitboard = 1 << index;
+  match colour {
+    Colour::White => {
+      let single = (pawn << 8) & !occupied;
+      single | ((single << 8) & !occupied & RANK_4)
+    }
+    Colour::Black => {
+      let single = (pawn >> 8) & !occupied;
+      single | ((single >> 8) & !occupied & RANK_5)
+    }
+  }
+}
+
+fn side(colour: Colour) -> usize {
+  match colour {
+    Colour::White => 0,
+    Colour::Black => 1,
+  }
+}
+
+// where every piece is, kept next to the board so the move generation never scans it
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub(crate) struct Bitboards {
+  // for each colour, white first, one for each kind in the order of PieceKind
+  pieces: [[Bitboard; 6]; 2],
+  colours: [Bitboard; 2],
+}
+
+impl Bitboards {
+  pub(crate) fn from_board(board: &[[Option<Piece>; 8]; 8]) -> Bitboards {
+    let mut bitboards = Bitboards::default();
+    for (index, tile) in board.iter().flatten().enumerate() {
+      if let Some(piece) = tile {
+        bitboards.toggle(index, *piece);
+      }
+    }
+    bitboards
+  }
+
+  // puts the piece on the tile, or takes it off if it is already there
+  pub(crate) fn toggle(&mut self, index: usize, piece: Piece) {
+    let side = side(piece.get_colour());
+    self.pieces[side][piece.get_kind() as usize] ^= 1 << index;
+    self.colours[side] ^= 1 << index;
+  }
+
+  pub(crate) fn get(&self, piece: Piece) -> Bitboard {
+    self.pieces[side(piece.get_colour())][piece.get_kind() as usize]
+  }
+
+  pub(crate) fn get_colour(&self, colour: Colour) -> Bitboard {
+    self.colours[side(colour)]
+  }
+
+  pub(crate) fn occupied(&self) -> Bitboard {
+    self.colours[0] | self.colours[1]
+  }
+
+  pub(crate) fn get_king(&self, colour: Colour) -> usize {
+    self.get(Piece::King(colour)).trailing_zeros() as usize
+  }
+
+  // the pieces of the colour that attack the tile
+  pub(crate) fn attackers(&self, index: usize, colour: Colour, occupied: Bitboard) -> Bitboard {
+    let queens = self.get(Piece::Queen(colour));
+    // a pawn attacks the tiles a pawn of the other colour would attack it from
+    (pawn_attacks(colour.opponent(), index) & self.get(Piece::Pawn(colour)))
+      | (KNIGHT_ATTACKS[index] & self.get(Piece::Knight(colour)))
+      | (KING_ATTACKS[index] & self.get(Piece::King(colour)))
+      | (slide(index, occupied, &BISHOP_DIRECTIONS) & (self.get(Piece::Bishop(colour)) | queens))
+      | (slide(index, occupied, &ROOK_DIRECTIONS) & (self.get(Piece::Rook(colour)) | queens))
+  }
+}
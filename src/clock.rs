@@ -0,0 +1,292 @@
+use crate::{Colour, Game, GameResult, GameState, Piece, Termination};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// where a clock reads the time from, as the time passed since some fixed point
+pub trait TimeSource: fmt::Debug {
+  fn now(&self) -> Duration;
+}
+
+#[derive(Debug)]
+struct SystemTime(Instant);
+
+impl TimeSource for SystemTime {
+  fn now(&self) -> Duration {
+    self.0.elapsed()
+  }
+}
+
+// time that only moves when told to, for tests and replaying timed games
+#[derive(Debug, Default)]
+pub struct ManualTime {
+  now: Mutex<Duration>,
+}
+
+impl ManualTime {
+  pub fn new() -> ManualTime {
+    ManualTime::default()
+  }
+
+  pub fn advance(&self, time: Duration) {
+    *self.now.lock().unwrap() += time;
+  }
+}
+
+impl TimeSource for ManualTime {
+  fn now(&self) -> Duration {
+    *self.now.lock().unwrap()
+  }
+}
+
+// what a side gets for every move on top of the time of the period
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimeBonus {
+  None,
+  // Fischer, added after every move
+  Increment(Duration),
+  // added after every move, but never more than the move took
+  Bronstein(Duration),
+  // the clock waits this long before it starts counting down on every move
+  SimpleDelay(Duration),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimePeriod {
+  // the moves to make in the period, or None for the rest of the game
+  pub moves: Option<u32>,
+  pub time: Duration,
+  pub bonus: TimeBonus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+  periods: Vec<TimePeriod>,
+}
+
+impl TimeControl {
+  // the time of each period is added when the one before it ends,
+  // and the last one starts over if it has a number of moves
+  pub fn new(periods: Vec<TimePeriod>) -> Option<TimeControl> {
+    if periods.is_empty() {
+      return None;
+    }
+    Some(TimeControl { periods })
+  }
+
+  pub fn sudden_death(time: Duration) -> TimeControl {
+    TimeControl::with_bonus(time, TimeBonus::None)
+  }
+
+  pub fn increment(time: Duration, increment: Duration) -> TimeControl {
+    TimeControl::with_bonus(time, TimeBonus::Increment(increment))
+  }
+
+  pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+    TimeControl::with_bonus(time, TimeBonus::Bronstein(delay))
+  }
+
+  pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+    TimeControl::with_bonus(time, TimeBonus::SimpleDelay(delay))
+  }
+
+  fn with_bonus(time: Duration, bonus: TimeBonus) -> TimeControl {
+    TimeControl {
+      periods: vec![TimePeriod {
+        moves: None,
+        time,
+        bonus,
+      }],
+    }
+  }
+}
+
+// what pressing the clock changed for the side that moved, see Clock::take_back
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Press {
+  period: usize,
+  moves_in_period: u32,
+  // the bonus and the time of a new period that came with the move
+  added: Duration,
+}
+
+// a clock for each side, the one of the side to move runs from the first move on
+#[derive(Debug, Clone)]
+pub struct Clock {
+  control: TimeControl,
+  // shared, so a game with a clock can still go to another thread
+  source: Arc<dyn TimeSource + Send + Sync>,
+  // white first, then black
+  remaining: [Duration; 2],
+  period: [usize; 2],
+  moves_in_period: [u32; 2],
+  // the side whose clock runs and when it started
+  running: Option<(Colour, Duration)>,
+}
+
+impl Clock {
+  pub fn new(control: TimeControl) -> Clock {
+    Clock::with_time_source(control, Arc::new(SystemTime(Instant::now())))
+  }
+
+  pub fn with_time_source(
+    control: TimeControl,
+    source: Arc<dyn TimeSource + Send + Sync>,
+  ) -> Clock {
+    let time = control.periods[0].time;
+    Clock {
+      control,
+      source,
+      remaining: [time, time],
+      period: [0, 0],
+      moves_in_period: [0, 0],
+      running: None,
+    }
+  }
+
+  // the time the side has left, counting the move it is thinking about
+  pub fn get_time_left(&self, colour: Colour) -> Duration {
+    let side = Clock::index(colour);
+    match self.running {
+      Some((running, started)) if running == colour => {
+        let used = self.charged(colour, self.source.now().saturating_sub(started));
+        self.remaining[side].saturating_sub(used)
+      }
+      _ => self.remaining[side],
+    }
+  }
+
+  // the side whose time ran out, it can only be the one whose clock runs
+  pub fn get_flagged(&self) -> Option<Colour> {
+    match self.running {
+      Some((colour, _)) if self.get_time_left(colour) == Duration::from_secs(0) => Some(colour),
+      _ => None,
+    }
+  }
+
+  // stops the clock of the side that moved and starts the other one
+  pub(crate) fn press(&mut self, colour: Colour) -> Press {
+    let side = Clock::index(colour);
+    let now = self.source.now();
+    let period = self.period[side];
+    let moves_in_period = self.moves_in_period[side];
+    // the first move of the game is made before the clock starts, but gets the bonus too
+    let elapsed = match self.running {
+      Some((running, started)) if running == colour => {
+        self.remaining[side] = self.get_time_left(colour);
+        now.saturating_sub(started)
+      }
+      _ => Duration::from_secs(0),
+    };
+    let mut added = match self.current_period(colour).bonus {
+      TimeBonus::Increment(increment) => increment,
+      TimeBonus::Bronstein(delay) => delay.min(elapsed),
+      _ => Duration::from_secs(0),
+    };
+
+    self.moves_in_period[side] += 1;
+    if Some(self.moves_in_period[side]) == self.current_period(colour).moves {
+      // the last period starts over when it is done
+      self.period[side] = (self.period[side] + 1).min(self.control.periods.len() - 1);
+      self.moves_in_period[side] = 0;
+      added += self.current_period(colour).time;
+    }
+    self.remaining[side] += added;
+    self.running = Some((colour.opponent(), now));
+    Press {
+      period,
+      moves_in_period,
+      added,
+    }
+  }
+
+  // puts the side back where it was in the time control before the move that is
+  // taken back, without the bonus or the time of a new period the move brought
+  pub(crate) fn take_back(&mut self, colour: Colour, press: Press) {
+    let side = Clock::index(colour);
+    self.remaining[side] = self.remaining[side].saturating_sub(press.added);
+    self.period[side] = press.period;
+    self.moves_in_period[side] = press.moves_in_period;
+  }
+
+  // hands the clock to the side without a bonus, like when a move is taken back
+  pub(crate) fn switch_to(&mut self, colour: Colour) {
+    if let Some((running, _)) = self.running {
+      let side = Clock::index(running);
+      self.remaining[side] = self.get_time_left(running);
+      self.running = Some((colour, self.source.now()));
+    }
+  }
+
+  fn current_period(&self, colour: Colour) -> TimePeriod {
+    self.control.periods[self.period[Clock::index(colour)]]
+  }
+
+  // the part of the time used that counts against the clock
+  fn charged(&self, colour: Colour, elapsed: Duration) -> Duration {
+    match self.current_period(colour).bonus {
+      TimeBonus::SimpleDelay(delay) => elapsed.saturating_sub(delay),
+      _ => elapsed,
+    }
+  }
+
+  fn index(colour: Colour) -> usize {
+    match colour {
+      Colour::White => 0,
+      Colour::Black => 1,
+    }
+  }
+}
+
+impl Game {
+  pub fn set_clock(&mut self, clock: Clock) {
+    self.clock = Some(clock);
+  }
+
+  pub fn get_clock(&self) -> Option<&Clock> {
+    self.clock.as_ref()
+  }
+
+  // ends the game if the side to move ran out of time, returns true if it did
+  pub fn check_time(&mut self) -> bool {
+    if self.is_game_over() {
+      return false;
+    }
+    let flagged = match self.clock.as_ref().and_then(|clock| clock.get_flagged()) {
+      Some(colour) => colour,
+      None => return false,
+    };
+    // running out of time only loses if the other side could still mate
+    let result = match self.can_mate(flagged.opponent()) {
+      true => GameResult::win_for(flagged.opponent()),
+      false => GameResult::Draw,
+    };
+    self.state = GameState::GameOver;
+    self.outcome = Some((result, Termination::Timeout));
+    true
+  }
+
+  // a bare king can never mate, and a king with one knight or bishop only can
+  // if the other side has more than its king to get in the way
+  fn can_mate(&self, colour: Colour) -> bool {
+    let (mut own, mut other) = (0, 0);
+    let mut only_minors = true;
+    for piece in self.board.iter().flatten().flatten() {
+      match piece {
+        Piece::King(_) => {}
+        piece if piece.get_colour() == colour => {
+          own += 1;
+          if let Piece::Pawn(_) | Piece::Rook(_) | Piece::Queen(_) = piece {
+            only_minors = false;
+          }
+        }
+        _ => other += 1,
+      }
+    }
+    match own {
+      0 => false,
+      1 if only_minors => other > 0,
+      _ => true,
+    }
+  }
+}
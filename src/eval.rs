@@ -0,0 +1,330 @@
+use crate::bitboard::{self, Bitboard};
+use crate::{Colour, Game, Piece, PieceKind};
+
+// the material left for each kind in the order of PieceKind, 24 is the whole middlegame
+const PHASE: [i32; 6] = [0, 2, 1, 4, 1, 0];
+const MAX_PHASE: i32 = 24;
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+// every weight is a pair of its middlegame and endgame value in centipawns, and the two
+// are blended by how much material is left, lists of weights go in the order of PieceKind
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalWeights {
+  pub piece_values: [(i32, i32); 6],
+  // from white's side with a8 first as the board is printed, black uses them mirrored
+  pub piece_square: [[(i32, i32); 64]; 6],
+  // for each tile the piece can move to
+  pub mobility: [(i32, i32); 6],
+  pub doubled_pawn: (i32, i32),
+  pub isolated_pawn: (i32, i32),
+  // by the rank the pawn is on, counted from the side's own first rank
+  pub passed_pawn: [(i32, i32); 8],
+  pub bishop_pair: (i32, i32),
+  // for each pawn on the three files around the king, one or two ranks in front of it
+  pub pawn_shield: (i32, i32),
+  // for each piece attacking a tile next to the other king
+  pub king_attack: (i32, i32),
+}
+
+impl Default for EvalWeights {
+  fn default() -> EvalWeights {
+    DEFAULT_WEIGHTS
+  }
+}
+
+// how good the position is for the side to move, in centipawns
+pub fn evaluate(game: &Game) -> i32 {
+  evaluate_with(game, &DEFAULT_WEIGHTS)
+}
+
+pub fn evaluate_with(game: &Game, weights: &EvalWeights) -> i32 {
+  let mut middlegame = 0;
+  let mut endgame = 0;
+  let mut phase = 0;
+  for colour in [Colour::White, Colour::Black].iter() {
+    let sign = match colour {
+      Colour::White => 1,
+      Colour::Black => -1,
+    };
+    let side = Side::evaluate(game, *colour, weights);
+    middlegame += sign * side.middlegame;
+    endgame += sign * side.endgame;
+    phase += side.phase;
+  }
+
+  // promotions can take the phase past the start of the game
+  let phase = phase.min(MAX_PHASE);
+  let score = (middlegame * phase + endgame * (MAX_PHASE - phase)) / MAX_PHASE;
+  match game.turn {
+    Colour::White => score,
+    Colour::Black => -score,
+  }
+}
+
+// the score of one side, before the two parts are blended
+#[derive(Default)]
+struct Side {
+  middlegame: i32,
+  endgame: i32,
+  phase: i32,
+}
+
+impl Side {
+  fn evaluate(game: &Game, colour: Colour, weights: &EvalWeights) -> Side {
+    let mut side = Side::default();
+    let bitboards = &game.bitboards;
+    let own = bitboards.get_colour(colour);
+    let occupied = bitboards.occupied();
+    let enemy_king = bitboards.get_king(colour.opponent());
+    let king_zone = bitboard::attacks(Piece::King(colour.opponent()), enemy_king, 0);
+
+    let kinds = [
+      PieceKind::King,
+      PieceKind::Rook,
+      PieceKind::Bishop,
+      PieceKind::Queen,
+      PieceKind::Knight,
+      PieceKind::Pawn,
+    ];
+    for kind in kinds.iter() {
+      let piece = kind.with_colour(colour);
+      let kind = *kind as usize;
+      for index in bitboard::tiles(bitboards.get(piece)) {
+        side.add(weights.piece_values[kind], 1);
+        side.add(weights.piece_square[kind][table_index(colour, index)], 1);
+        side.phase += PHASE[kind];
+        if let Piece::Pawn(_) | Piece::King(_) = piece {
+          continue;
+        }
+        let attacks = bitboard::attacks(piece, index, occupied);
+        side.add(weights.mobility[kind], (attacks & !own).count_ones() as i32);
+        if attacks & king_zone != 0 {
+          side.add(weights.king_attack, 1);
+        }
+      }
+    }
+
+    if bitboards.get(Piece::Bishop(colour)).count_ones() >= 2 {
+      side.add(weights.bishop_pair, 1);
+    }
+    side.add_pawn_structure(game, colour, weights);
+    side.add_pawn_shield(game, colour, weights);
+    side
+  }
+
+  fn add(&mut self, weight: (i32, i32), times: i32) {
+    self.middlegame += weight.0 * times;
+    self.endgame += weight.1 * times;
+  }
+
+  fn add_pawn_structure(&mut self, game: &Game, colour: Colour, weights: &EvalWeights) {
+    let pawns = game.bitboards.get(Piece::Pawn(colour));
+    let enemy_pawns = game.bitboards.get(Piece::Pawn(colour.opponent()));
+    for file in 0..8 {
+      let on_file = (pawns & (FILE_A << file)).count_ones() as i32;
+      if on_file > 1 {
+        self.add(weights.doubled_pawn, on_file - 1);
+      }
+      if on_file > 0 && pawns & neighbour_files(file) == 0 {
+        self.add(weights.isolated_pawn, on_file);
+      }
+    }
+
+    for index in bitboard::tiles(pawns) {
+      let (row, file) = (index / 8, index % 8);
+      // no pawn of the other side can stop it or take it on the way
+      let files = neighbour_files(file) | (FILE_A << file);
+      if enemy_pawns & files & ahead(colour, row) == 0 {
+        let rank = match colour {
+          Colour::White => row,
+          Colour::Black => 7 - row,
+        };
+        self.add(weights.passed_pawn[rank], 1);
+      }
+    }
+  }
+
+  fn add_pawn_shield(&mut self, game: &Game, colour: Colour, weights: &EvalWeights) {
+    let king = game.bitboards.get_king(colour);
+    let (row, file) = (king / 8, king % 8);
+    let files = neighbour_files(file) | (FILE_A << file);
+    let rows = match colour {
+      Colour::White => ahead(colour, row) & !ahead(colour, row + 2),
+      Colour::Black => ahead(colour, row) & !ahead(colour, row.saturating_sub(2)),
+    };
+    let shield = game.bitboards.get(Piece::Pawn(colour)) & files & rows;
+    self.add(weights.pawn_shield, shield.count_ones() as i32);
+  }
+}
+
+fn neighbour_files(file: usize) -> Bitboard {
+  let left = if file > 0 { FILE_A << (file - 1) } else { 0 };
+  let right = if file < 7 { FILE_A << (file + 1) } else { 0 };
+  left | right
+}
+
+// the rows in front of the row, as seen by the side
+fn ahead(colour: Colour, row: usize) -> Bitboard {
+  match colour {
+    Colour::White if row >= 7 => 0,
+    Colour::White => !0 << (8 * (row + 1)),
+    Colour::Black => (1 << (8 * row)) - 1,
+  }
+}
+
+// the tables are written from white's side with a8 first
+fn table_index(colour: Colour, index: usize) -> usize {
+  match colour {
+    Colour::White => (7 - index / 8) * 8 + index % 8,
+    Colour::Black => index,
+  }
+}
+
+const fn pair_tables(middlegame: [[i32; 64]; 6], endgame: [[i32; 64]; 6]) -> [[(i32, i32); 64]; 6] {
+  let mut tables = [[(0, 0); 64]; 6];
+  let mut kind = 0;
+  while kind < 6 {
+    let mut index = 0;
+    while index < 64 {
+      tables[kind][index] = (middlegame[kind][index], endgame[kind][index]);
+      index += 1;
+    }
+    kind += 1;
+  }
+  tables
+}
+
+#[rustfmt::skip]
+const KING_MIDDLEGAME: [i32; 64] = [
+  -30, -40, -40, -50, -50, -40, -40, -30,
+  -30, -40, -40, -50, -50, -40, -40, -30,
+  -30, -40, -40, -50, -50, -40, -40, -30,
+  -30, -40, -40, -50, -50, -40, -40, -30,
+  -20, -30, -30, -40, -40, -30, -30, -20,
+  -10, -20, -20, -20, -20, -20, -20, -10,
+   20,  20,   0,   0,   0,   0,  20,  20,
+   20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME: [i32; 64] = [
+  -50, -40, -30, -20, -20, -30, -40, -50,
+  -30, -20, -10,   0,   0, -10, -20, -30,
+  -30, -10,  20,  30,  30,  20, -10, -30,
+  -30, -10,  30,  40,  40,  30, -10, -30,
+  -30, -10,  30,  40,  40,  30, -10, -30,
+  -30, -10,  20,  30,  30,  20, -10, -30,
+  -30, -30,   0,   0,   0,   0, -30, -30,
+  -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+   0,  0,  0,  0,  0,  0,  0,  0,
+   5, 10, 10, 10, 10, 10, 10,  5,
+  -5,  0,  0,  0,  0,  0,  0, -5,
+  -5,  0,  0,  0,  0,  0,  0, -5,
+  -5,  0,  0,  0,  0,  0,  0, -5,
+  -5,  0,  0,  0,  0,  0,  0, -5,
+  -5,  0,  0,  0,  0,  0,  0, -5,
+   0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+  -20, -10, -10, -10, -10, -10, -10, -20,
+  -10,   0,   0,   0,   0,   0,   0, -10,
+  -10,   0,   5,  10,  10,   5,   0, -10,
+  -10,   5,   5,  10,  10,   5,   5, -10,
+  -10,   0,  10,  10,  10,  10,   0, -10,
+  -10,  10,  10,  10,  10,  10,  10, -10,
+  -10,   5,   0,   0,   0,   0,   5, -10,
+  -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+  -20, -10, -10, -5, -5, -10, -10, -20,
+  -10,   0,   0,  0,  0,   0,   0, -10,
+  -10,   0,   5,  5,  5,   5,   0, -10,
+   -5,   0,   5,  5,  5,   5,   0,  -5,
+    0,   0,   5,  5,  5,   5,   0,  -5,
+  -10,   5,   5,  5,  5,   5,   0, -10,
+  -10,   0,   5,  0,  0,   0,   0, -10,
+  -20, -10, -10, -5, -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+  -50, -40, -30, -30, -30, -30, -40, -50,
+  -40, -20,   0,   0,   0,   0, -20, -40,
+  -30,   0,  10,  15,  15,  10,   0, -30,
+  -30,   5,  15,  20,  20,  15,   5, -30,
+  -30,   0,  15,  20,  20,  15,   0, -30,
+  -30,   5,  10,  15,  15,  10,   5, -30,
+  -40, -20,   0,   5,   5,   0, -20, -40,
+  -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const PAWN_MIDDLEGAME: [i32; 64] = [
+   0,  0,   0,   0,   0,   0,  0,  0,
+  50, 50,  50,  50,  50,  50, 50, 50,
+  10, 10,  20,  30,  30,  20, 10, 10,
+   5,  5,  10,  25,  25,  10,  5,  5,
+   0,  0,   0,  20,  20,   0,  0,  0,
+   5, -5, -10,   0,   0, -10, -5,  5,
+   5, 10,  10, -20, -20,  10, 10,  5,
+   0,  0,   0,   0,   0,   0,  0,  0,
+];
+
+// in the endgame every step forward counts, the passed pawn weights do the rest
+#[rustfmt::skip]
+const PAWN_ENDGAME: [i32; 64] = [
+   0,  0,  0,  0,  0,  0,  0,  0,
+  60, 60, 60, 60, 60, 60, 60, 60,
+  40, 40, 40, 40, 40, 40, 40, 40,
+  25, 25, 25, 25, 25, 25, 25, 25,
+  15, 15, 15, 15, 15, 15, 15, 15,
+   5,  5,  5,  5,  5,  5,  5,  5,
+   0,  0,  0,  0,  0,  0,  0,  0,
+   0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const DEFAULT_WEIGHTS: EvalWeights = EvalWeights {
+  piece_values: [
+    (0, 0),
+    (477, 512),
+    (365, 297),
+    (1025, 936),
+    (337, 281),
+    (82, 94),
+  ],
+  piece_square: pair_tables(
+    [
+      KING_MIDDLEGAME,
+      ROOK,
+      BISHOP,
+      QUEEN,
+      KNIGHT,
+      PAWN_MIDDLEGAME,
+    ],
+    [KING_ENDGAME, ROOK, BISHOP, QUEEN, KNIGHT, PAWN_ENDGAME],
+  ),
+  mobility: [(0, 0), (2, 4), (4, 4), (1, 2), (4, 4), (0, 0)],
+  doubled_pawn: (-10, -20),
+  isolated_pawn: (-10, -15),
+  passed_pawn: [
+    (0, 0),
+    (5, 10),
+    (10, 20),
+    (15, 35),
+    (25, 60),
+    (40, 90),
+    (60, 130),
+    (0, 0),
+  ],
+  bishop_pair: (30, 50),
+  pawn_shield: (10, 0),
+  king_attack: (8, 2),
+};
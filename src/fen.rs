@@ -0,0 +1,319 @@
+use crate::{Bitboards, Castling, Colour, Game, GameState, Piece, Position, PositionError};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FenField {
+  Placement,
+  SideToMove,
+  Castling,
+  EnPassant,
+  HalfmoveClock,
+  FullmoveNumber,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FenError {
+  MissingField(FenField),
+  TooManyFields,
+  // the placement has to describe exactly 8 ranks
+  RankCount(usize),
+  // ranks are numbered 1-8 like on the board
+  FileCount { rank: usize, files: usize },
+  InvalidCharacter { field: FenField, character: char },
+  InvalidValue { field: FenField, value: String },
+  // the string is well formed but the position can't come up in a game
+  IllegalPosition(PositionError),
+}
+
+impl fmt::Display for FenField {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let name = match self {
+      FenField::Placement => "piece placement",
+      FenField::SideToMove => "side to move",
+      FenField::Castling => "castling rights",
+      FenField::EnPassant => "en passant target",
+      FenField::HalfmoveClock => "halfmove clock",
+      FenField::FullmoveNumber => "fullmove number",
+    };
+    write!(f, "{}", name)
+  }
+}
+
+impl fmt::Display for FenError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FenError::MissingField(field) => write!(f, "the {} field is missing", field),
+      FenError::TooManyFields => write!(f, "there are more than six fields"),
+      FenError::RankCount(ranks) => write!(f, "expected 8 ranks but found {}", ranks),
+      FenError::FileCount { rank, files } => {
+        write!(f, "rank {} has {} files instead of 8", rank, files)
+      }
+      FenError::InvalidCharacter { field, character } => {
+        write!(
+          f,
+          "invalid character '{}' in the {} field",
+          character, field
+        )
+      }
+      FenError::InvalidValue { field, value } => {
+        write!(f, "invalid value \"{}\" in the {} field", value, field)
+      }
+      FenError::IllegalPosition(err) => write!(f, "illegal position, {}", err),
+    }
+  }
+}
+
+impl std::error::Error for FenError {}
+
+impl Game {
+  pub fn try_from_fen(fen_string: &str) -> Result<Game, FenError> {
+    let fields: Vec<&str> = fen_string.split_whitespace().collect();
+    // the two clocks are often left out, every other field is needed
+    let required = [
+      FenField::Placement,
+      FenField::SideToMove,
+      FenField::Castling,
+      FenField::EnPassant,
+    ];
+    if fields.len() < required.len() {
+      return Err(FenError::MissingField(required[fields.len()]));
+    }
+    if fields.len() == 5 {
+      return Err(FenError::MissingField(FenField::FullmoveNumber));
+    }
+    if fields.len() > 6 {
+      return Err(FenError::TooManyFields);
+    }
+
+    let board = Game::parse_placement(fields[0])?;
+
+    let turn = match fields[1] {
+      "w" => Colour::White,
+      "b" => Colour::Black,
+      side => return Err(Game::invalid_value(FenField::SideToMove, side)),
+    };
+
+    let castling = Game::parse_castling(fields[2])?;
+
+    // the tile a pawn skipped over with its double step, if any
+    let en_passant = match fields[3] {
+      "-" => None,
+      tile => {
+        let target =
+          Game::parse_tile(tile).ok_or_else(|| Game::invalid_value(FenField::EnPassant, tile))?;
+        // the pawn that moved belongs to the side that isn't moving now
+        let expected_row = match turn {
+          Colour::White => 5,
+          Colour::Black => 2,
+        };
+        if target.0 != expected_row {
+          return Err(Game::invalid_value(FenField::EnPassant, tile));
+        }
+        Some(target)
+      }
+    };
+
+    // moves since the last capture or pawn move, and the number of the current full move
+    let (halfmove_clock, fullmove_number) = if fields.len() == 6 {
+      let halfmove_clock: u32 = fields[4]
+        .parse()
+        .map_err(|_| Game::invalid_value(FenField::HalfmoveClock, fields[4]))?;
+      let fullmove_number: u32 = fields[5]
+        .parse()
+        .map_err(|_| Game::invalid_value(FenField::FullmoveNumber, fields[5]))?;
+      if fullmove_number == 0 {
+        return Err(Game::invalid_value(FenField::FullmoveNumber, fields[5]));
+      }
+      (halfmove_clock, fullmove_number)
+    } else {
+      (0, 1)
+    };
+
+    let mut game = Game {
+      turn,
+      state: GameState::InProgress,
+      board,
+      bitboards: Bitboards::from_board(&board),
+      hash: 0,
+      castling,
+      en_passant,
+      halfmove_clock,
+      fullmove_number,
+      history: vec![],
+      moves: vec![],
+      starting_fen: String::new(),
+      tags: vec![],
+      undo_stack: vec![],
+      redo_stack: vec![],
+      outcome: None,
+      draw_offer: None,
+      clock: None,
+    };
+    game.validate().map_err(FenError::IllegalPosition)?;
+    game.hash = game.compute_hash();
+    game.starting_fen = game.to_fen();
+    game.record_position();
+    game.update_state();
+
+    Ok(game)
+  }
+
+  fn parse_placement(placement: &str) -> Result<[[Option<Piece>; 8]; 8], FenError> {
+    let mut board: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+      return Err(FenError::RankCount(ranks.len()));
+    }
+
+    // the first rank in the string is the eighth one on the board
+    for (i, rank) in ranks.iter().enumerate() {
+      let row = 7 - i;
+      let mut col = 0;
+      for ch in rank.chars() {
+        match (Piece::from_fen(ch), ch.to_digit(10)) {
+          (Some(piece), _) => {
+            if col < 8 {
+              board[row][col] = Some(piece);
+            }
+            col += 1;
+          }
+          (None, Some(empty)) if (1..=8).contains(&empty) => col += empty as usize,
+          _ => {
+            return Err(FenError::InvalidCharacter {
+              field: FenField::Placement,
+              character: ch,
+            })
+          }
+        }
+      }
+      if col != 8 {
+        return Err(FenError::FileCount {
+          rank: row + 1,
+          files: col,
+        });
+      }
+    }
+
+    Ok(board)
+  }
+
+  fn parse_castling(rights: &str) -> Result<Castling, FenError> {
+    let mut castling = Castling {
+      white_queen: false,
+      white_king: false,
+      black_queen: false,
+      black_king: false,
+    };
+    if rights == "-" {
+      return Ok(castling);
+    }
+    for c in rights.chars() {
+      let right = match c {
+        'Q' => &mut castling.white_queen,
+        'K' => &mut castling.white_king,
+        'q' => &mut castling.black_queen,
+        'k' => &mut castling.black_king,
+        _ => {
+          return Err(FenError::InvalidCharacter {
+            field: FenField::Castling,
+            character: c,
+          })
+        }
+      };
+      // every right can only be given once
+      if *right {
+        return Err(FenError::InvalidCharacter {
+          field: FenField::Castling,
+          character: c,
+        });
+      }
+      *right = true;
+    }
+
+    Ok(castling)
+  }
+
+  pub(crate) fn parse_tile(tile: &str) -> Option<Position> {
+    let chars: Vec<char> = tile.chars().collect();
+    if chars.len() != 2 {
+      return None;
+    }
+    let col = match chars[0] {
+      'a'..='h' => chars[0] as usize - 'a' as usize,
+      _ => return None,
+    };
+    let row = match chars[1] {
+      '1'..='8' => chars[1] as usize - '1' as usize,
+      _ => return None,
+    };
+    Some(Position(row, col))
+  }
+
+  fn invalid_value(field: FenField, value: &str) -> FenError {
+    FenError::InvalidValue {
+      field,
+      value: String::from(value),
+    }
+  }
+
+  pub fn to_fen(&self) -> String {
+    let mut ranks = vec![];
+    for row in self.board.iter().rev() {
+      let mut rank = String::new();
+      let mut empty = 0;
+      for maybe_piece in row {
+        match maybe_piece {
+          Some(piece) => {
+            if empty > 0 {
+              rank.push_str(&empty.to_string());
+              empty = 0;
+            }
+            rank.push(piece.to_fen());
+          }
+          None => empty += 1,
+        }
+      }
+      if empty > 0 {
+        rank.push_str(&empty.to_string());
+      }
+      ranks.push(rank);
+    }
+
+    let turn = match self.turn {
+      Colour::White => "w",
+      Colour::Black => "b",
+    };
+
+    let mut castling = String::new();
+    if self.castling.white_king {
+      castling.push('K');
+    }
+    if self.castling.white_queen {
+      castling.push('Q');
+    }
+    if self.castling.black_king {
+      castling.push('k');
+    }
+    if self.castling.black_queen {
+      castling.push('q');
+    }
+    if castling.is_empty() {
+      castling.push('-');
+    }
+
+    let en_passant = match &self.en_passant {
+      Some(target) => Game::parse_coordinates(target.clone()),
+      None => String::from("-"),
+    };
+
+    format!(
+      "{} {} {} {} {} {}",
+      ranks.join("/"),
+      turn,
+      castling,
+      en_passant,
+      self.halfmove_clock,
+      self.fullmove_number
+    )
+  }
+}
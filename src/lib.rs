@@ -1,6 +1,49 @@
+#![allow(
+  clippy::let_and_return,
+  clippy::needless_bool,
+  clippy::new_without_default,
+  clippy::wrong_self_convention
+)]
+
 use colored::*;
 use std::io;
+use std::time::Duration;
+mod bitboard;
+mod clock;
+mod eval;
+mod fen;
+mod moves;
+mod perft;
+mod pgn;
+mod protocol;
+mod result;
+mod san;
+mod search;
+mod square;
 mod tests;
+mod uci;
+mod undo;
+mod validation;
+mod xboard;
+mod zobrist;
+
+use bitboard::Bitboards;
+pub use clock::{Clock, ManualTime, TimeBonus, TimeControl, TimePeriod, TimeSource};
+pub use eval::{evaluate, evaluate_with, EvalWeights};
+pub use fen::{FenError, FenField};
+pub use moves::{Move, MoveFlags, PieceKind};
+pub use pgn::PgnError;
+pub use result::{GameResult, Termination};
+pub use search::{best_move, best_move_with, SearchLimits, SearchResult};
+pub use square::Square;
+pub use uci::uci;
+use undo::Undo;
+pub use validation::PositionError;
+pub use xboard::xboard;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+// how the engine introduces itself to chess GUIs
+const ENGINE_NAME: &str = concat!("alholmbe-chess ", env!("CARGO_PKG_VERSION"));
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum GameState {
@@ -8,6 +51,17 @@ pub enum GameState {
   Check,
   Checkmate,
   Stalemate,
+  // a draw the side to move can claim, see Game::claim_draw
+  FiftyMoveRule,
+  // a draw that ends the game on its own
+  SeventyFiveMoveRule,
+  // a draw the side to move can claim, see Game::claim_draw
+  ThreefoldRepetition,
+  // a draw that ends the game on its own
+  FivefoldRepetition,
+  // neither side has the pieces left to checkmate, the game is drawn
+  InsufficientMaterial,
+  // ended by a claimed or agreed draw or a resignation, see Game::get_result for how
   GameOver,
 }
 
@@ -20,6 +74,15 @@ pub enum Colour {
   Black,
 }
 
+impl Colour {
+  fn opponent(&self) -> Colour {
+    match self {
+      Colour::White => Colour::Black,
+      Colour::Black => Colour::White,
+    }
+  }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Piece {
   King(Colour),
@@ -49,6 +112,41 @@ impl Piece {
     }
   }
 
+  fn from_fen(ch: char) -> Option<Piece> {
+    match ch {
+      'r' => Some(Piece::Rook(Colour::Black)),
+      'n' => Some(Piece::Knight(Colour::Black)),
+      'b' => Some(Piece::Bishop(Colour::Black)),
+      'q' => Some(Piece::Queen(Colour::Black)),
+      'k' => Some(Piece::King(Colour::Black)),
+      'p' => Some(Piece::Pawn(Colour::Black)),
+      'R' => Some(Piece::Rook(Colour::White)),
+      'N' => Some(Piece::Knight(Colour::White)),
+      'B' => Some(Piece::Bishop(Colour::White)),
+      'Q' => Some(Piece::Queen(Colour::White)),
+      'K' => Some(Piece::King(Colour::White)),
+      'P' => Some(Piece::Pawn(Colour::White)),
+      _ => None,
+    }
+  }
+
+  fn to_fen(&self) -> char {
+    match self {
+      Piece::Pawn(Colour::White) => 'P',
+      Piece::Rook(Colour::White) => 'R',
+      Piece::Knight(Colour::White) => 'N',
+      Piece::Bishop(Colour::White) => 'B',
+      Piece::Queen(Colour::White) => 'Q',
+      Piece::King(Colour::White) => 'K',
+      Piece::Pawn(Colour::Black) => 'p',
+      Piece::Rook(Colour::Black) => 'r',
+      Piece::Knight(Colour::Black) => 'n',
+      Piece::Bishop(Colour::Black) => 'b',
+      Piece::Queen(Colour::Black) => 'q',
+      Piece::King(Colour::Black) => 'k',
+    }
+  }
+
   fn to_ascii(&self) -> char {
     match self {
       Piece::Pawn(Colour::White) => '\u{2659}',
@@ -75,113 +173,53 @@ struct Castling {
   black_king: bool,
 }
 
+// everything that has to match for two positions to count as a repetition
+#[derive(Debug, PartialEq, Clone)]
+struct PositionRecord {
+  board: [[Option<Piece>; 8]; 8],
+  turn: Colour,
+  castling: Castling,
+  en_passant: Option<Position>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Game {
   state: GameState,
-  name: String,
   board: [[Option<Piece>; 8]; 8],
+  // the same pieces as the board, for generating moves
+  bitboards: Bitboards,
+  // the Zobrist key, updated with every change, see Game::hash
+  hash: u64,
   turn: Colour,
   castling: Castling,
-  promote: (bool, String),
+  en_passant: Option<Position>,
+  halfmove_clock: u32,
+  fullmove_number: u32,
+  history: Vec<PositionRecord>,
+  // every move made so far in SAN, and what is needed to write them down as PGN
+  moves: Vec<String>,
+  starting_fen: String,
+  tags: Vec<(String, String)>,
+  // the moves that can be taken back, and the ones taken back that can be made again
+  undo_stack: Vec<Undo>,
+  redo_stack: Vec<Move>,
+  // who won and why, once the game is over
+  outcome: Option<(GameResult, Termination)>,
+  draw_offer: Option<Colour>,
+  clock: Option<Clock>,
 }
 
 impl Game {
   pub fn new() -> Game {
-    let standard_starting_board =
-      String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let standard_starting_board = String::from(STARTING_FEN);
     Game::new_from_fen(standard_starting_board)
   }
 
   pub fn new_from_fen(fen_string: String) -> Game {
-    let mut board: [[Option<Piece>; 8]; 8] = [[None; 8]; 8];
-    let lines: Vec<&str> = fen_string.split(' ').collect();
-    let positions: Vec<&str> = lines[0].split('/').collect();
-
-    let mut i = 7;
-    for row in 0..=7 {
-      let mut col = 0;
-      for ch in positions[i].chars() {
-        board[row][col] = match ch {
-          'r' => Some(Piece::Rook(Colour::Black)),
-          'n' => Some(Piece::Knight(Colour::Black)),
-          'b' => Some(Piece::Bishop(Colour::Black)),
-          'q' => Some(Piece::Queen(Colour::Black)),
-          'k' => Some(Piece::King(Colour::Black)),
-          'p' => Some(Piece::Pawn(Colour::Black)),
-          'R' => Some(Piece::Rook(Colour::White)),
-          'N' => Some(Piece::Knight(Colour::White)),
-          'B' => Some(Piece::Bishop(Colour::White)),
-          'Q' => Some(Piece::Queen(Colour::White)),
-          'K' => Some(Piece::King(Colour::White)),
-          'P' => Some(Piece::Pawn(Colour::White)),
-          _ => {
-            col += ch.to_digit(10).unwrap() as usize - 1;
-            None
-          }
-        };
-        col += 1;
-      }
-
-      if i != 0 {
-        i -= 1;
-      }
-    }
-
-    let turn = match lines[1] {
-      "w" => Colour::White,
-      _ => Colour::Black,
-    };
-
-    let mut castling = Castling {
-      white_queen: false,
-      white_king: false,
-      black_queen: false,
-      black_king: false,
-    };
-    for c in lines[2].chars() {
-      match c {
-        '-' => break,
-        'Q' => castling.white_queen = true,
-        'K' => castling.white_king = true,
-        'q' => castling.black_queen = true,
-        'k' => castling.black_king = true,
-        _ => break,
-      };
+    match Game::try_from_fen(&fen_string) {
+      Ok(game) => game,
+      Err(err) => panic!("invalid FEN string: {}", err),
     }
-
-    // println!("en passant targets = {}", lines[3]);
-
-    // println!("halfmoves = {}", lines[4]);
-
-    // println!("fullmoves = {}", lines[5]);
-
-    let mut game = Game {
-      turn,
-      name: String::from("yoo"),
-      state: GameState::InProgress,
-      board,
-      castling,
-      promote: (false, String::new()),
-    };
-
-    if game.turn == Colour::White {
-      if game.check(String::from("white")) {
-        game.state = GameState::Check;
-      }
-    } else {
-      if game.check(String::from("black")) {
-        game.state = GameState::Check;
-      }
-    }
-
-    let exists = game.move_exists();
-    if !exists && game.state == GameState::Check {
-      game.state = GameState::Checkmate;
-    } else if !exists && game.state == GameState::InProgress {
-      game.state = GameState::Stalemate;
-    }
-
-    game
   }
 
   fn print_board(&self) {
@@ -207,15 +245,21 @@ impl Game {
 
   pub fn play() {
     let mut game = Game::new();
-    while game.state != GameState::GameOver {
+    while !game.is_game_over() {
       game.print_board();
       println!("STATE OF THE GAME = {:?}", game.get_game_state());
+      if let Some(colour) = game.get_draw_offer() {
+        if colour != game.turn {
+          let offer = format!("{:?} offers a draw, type accept or decline", colour);
+          println!("{}", offer.green());
+        }
+      }
       match game.turn {
         Colour::White => {
-          println!("move for white (from, to) EXAMPLE a2a4: ");
+          println!("move for white (from, to) EXAMPLE a2a4 or Nf3: ");
         }
         Colour::Black => {
-          println!("move for black (from, to) EXAMPLE a7a5: ");
+          println!("move for black (from, to) EXAMPLE a7a5 or Nf6: ");
         }
       };
       let mut mv = String::new();
@@ -228,6 +272,55 @@ impl Game {
       if actual_move == "quit" {
         break;
       }
+      if actual_move == "draw" {
+        if !game.claim_draw() {
+          println!("{}", "there is no draw to claim!".red());
+        }
+        continue;
+      }
+      if actual_move == "undo" {
+        if !game.undo() {
+          println!("{}", "there is no move to undo!".red());
+        }
+        continue;
+      }
+      if actual_move == "redo" {
+        if !game.redo() {
+          println!("{}", "there is no move to redo!".red());
+        }
+        continue;
+      }
+      if actual_move == "engine" {
+        match best_move(&game, SearchLimits::time(Duration::from_secs(2))) {
+          Some(result) => {
+            let san = game.move_to_san(&result.best_move).unwrap();
+            game.apply_move(&result.best_move);
+            println!("{}", format!("the engine plays {}", san).green());
+          }
+          None => println!("{}", "there is no move to make!".red()),
+        }
+        continue;
+      }
+      if actual_move == "resign" {
+        game.resign(game.turn);
+        continue;
+      }
+      if actual_move == "offer" {
+        game.offer_draw(game.turn);
+        println!("{}", "draw offered, make your move".green());
+        continue;
+      }
+      if actual_move == "accept" || actual_move == "decline" {
+        let answered = match game.get_draw_offer() {
+          Some(_) if actual_move == "accept" => game.accept_draw(),
+          Some(colour) if colour != game.turn => game.decline_draw(),
+          _ => false,
+        };
+        if !answered {
+          println!("{}", "there is no draw offer to answer!".red());
+        }
+        continue;
+      }
       let mut from = String::from("");
       let mut to = String::from("");
       for (i, c) in actual_move.chars().enumerate() {
@@ -239,56 +332,73 @@ impl Game {
       }
       // kolla ifall from och to ar legit moves
       if !Game::check_input(from.clone(), to.clone()) {
-        println!("{}", "illegal input!".red());
-        continue;
-      }
-      println!("from: {}, to: {}", from, to);
-      match game.make_move(from.clone(), to.clone()) {
-        Some(_) => {}
-        None => {
-          println!("{}", "illegal move!".red());
+        if game.make_typed_move(&actual_move).is_none() {
+          println!("{}", "illegal input!".red());
           continue;
         }
-      }
-
-      if game.promote.0 {
-        loop {
-          println!(
-            "{}",
-            "Promote your pawn! q for queen, r for rook, k for knight and b for bishop: ".green()
-          );
-          let mut promotion = String::new();
-          io::stdin()
-            .read_line(&mut promotion)
-            .expect("Failed to read line");
-          let promotion: String = match promotion.trim().parse() {
-            Ok(piece) => piece,
-            Err(_) => continue,
-          };
-          if promotion == String::from("q") {
-            game.set_promotion(game.promote.1.clone(), 'q');
-            break;
-          } else if promotion == String::from("r") {
-            game.set_promotion(game.promote.1.clone(), 'r');
-            break;
-          } else if promotion == String::from("k") {
-            game.set_promotion(game.promote.1.clone(), 'k');
-            break;
-          } else if promotion == String::from("b") {
-            game.set_promotion(game.promote.1.clone(), 'b');
-            break;
-          } else {
-            println!("{}", "Only acceptable input is q, r, k or b.".red());
+      } else {
+        println!("from: {}, to: {}", from, to);
+        // the piece a pawn is promoted to is asked for before the move is made
+        let mut promotion = None;
+        let from_square = Square::from_name(&from).unwrap();
+        let to_square = Square::from_name(&to).unwrap();
+        if game
+          .get_legal_moves(from_square)
+          .iter()
+          .any(|legal| legal.to == to_square && legal.promotion.is_some())
+        {
+          while promotion.is_none() {
+            println!(
+              "{}",
+              "Promote your pawn! q for queen, r for rook, n for knight and b for bishop: ".green()
+            );
+            let mut piece = String::new();
+            io::stdin()
+              .read_line(&mut piece)
+              .expect("Failed to read line");
+            promotion = match piece.trim() {
+              "q" => Some(PieceKind::Queen),
+              "r" => Some(PieceKind::Rook),
+              "n" => Some(PieceKind::Knight),
+              "b" => Some(PieceKind::Bishop),
+              _ => {
+                println!("{}", "Only acceptable input is q, r, n or b.".red());
+                None
+              }
+            };
+          }
+        }
+        match game.make_move_promoting(from.clone(), to.clone(), promotion) {
+          Some(_) => {}
+          None => {
+            println!("{}", "illegal move!".red());
+            continue;
           }
         }
       }
-      game.promote = (false, String::new());
 
-      if game.state == GameState::Checkmate || game.state == GameState::Stalemate {
-        println!("THE RESULT OF THE GAME IS = {:?}", game.state);
-        game.state = GameState::GameOver;
+      match game.state {
+        GameState::FiftyMoveRule => println!(
+          "{}",
+          "50 moves without a capture or pawn move, type draw to claim a draw".green()
+        ),
+        GameState::ThreefoldRepetition => println!(
+          "{}",
+          "the same position has come up three times, type draw to claim a draw".green()
+        ),
+        _ if game.can_claim_draw() => {
+          println!("{}", "type draw to claim a draw".green())
+        }
+        _ => {}
       }
     }
+    if let (Some(result), Some(termination)) = (game.get_result(), game.get_termination()) {
+      println!(
+        "THE RESULT OF THE GAME IS = {} by {:?}",
+        result, termination
+      );
+    }
+    println!("\n{}", game.to_pgn());
   }
 
   fn check_input(from: String, to: String) -> bool {
@@ -310,7 +420,7 @@ impl Game {
   }
 
   fn get_piece_at(&self, position: String) -> Option<Piece> {
-    let pos = Game::parse_string(&position);
+    let pos = Game::parse_tile(&position)?;
     self.board[pos.0][pos.1]
   }
 
@@ -324,11 +434,26 @@ impl Game {
     tiles
   }
 
+  // a pawn reaching the back rank becomes a queen, see apply_move for the other pieces
   pub fn make_move(&mut self, _from: String, _to: String) -> Option<GameState> {
-    let old_position = Game::parse_string(&_from);
-    let new_position = Game::parse_string(&_to);
+    self.make_move_promoting(_from, _to, None)
+  }
+
+  // the promotion is part of the move, so the state is computed with the promoted piece
+  pub(crate) fn make_move_promoting(
+    &mut self,
+    _from: String,
+    _to: String,
+    promotion: Option<PieceKind>,
+  ) -> Option<GameState> {
+    // a side out of time can't move anymore
+    if self.is_game_over() || self.check_time() {
+      return None;
+    }
+    let old_position = Game::parse_tile(&_from)?;
+    let new_position = Game::parse_tile(&_to)?;
     // Can't move a None piece
-    let moving_piece = self.board[old_position.0][old_position.1]?;
+    let moving_piece = self.get_piece_at(_from.clone())?;
     if moving_piece.get_colour() != self.turn {
       return None;
     }
@@ -342,29 +467,137 @@ impl Game {
         break;
       }
     }
+    let promotes = matches!(
+      (new_position.0, moving_piece),
+      (7, Piece::Pawn(Colour::White)) | (0, Piece::Pawn(Colour::Black))
+    );
+    let promotion = match (promotes, promotion) {
+      (_, Some(PieceKind::King)) | (_, Some(PieceKind::Pawn)) | (false, Some(_)) => return None,
+      (true, kind) => Some(kind.unwrap_or(PieceKind::Queen).with_colour(self.turn)),
+      (false, None) => None,
+    };
+    // written down before the board changes, the suffix needs the board after
+    let san = self.san_without_suffix(&old_position, &new_position, promotion);
     if make_move {
-      self.board[new_position.0][new_position.1] = Some(moving_piece);
-      self.board[old_position.0][old_position.1] = None;
-      // check for promotion
-      match (new_position.0, moving_piece) {
-        (7, Piece::Pawn(Colour::White)) => {
-          self.promote = (true, _to);
-        }
-        (0, Piece::Pawn(Colour::Black)) => {
-          self.promote = (true, _to);
-        }
-        _ => {}
-      };
-      self.turn = match self.turn {
-        Colour::White => Colour::Black,
-        Colour::Black => Colour::White,
-      };
+      self.move_pieces(&old_position, &new_position, moving_piece, promotion);
+      self.redo_stack.clear();
+      // moving instead of accepting turns down the offer of the side to move next
+      if self.draw_offer == Some(self.turn) {
+        self.draw_offer = None;
+      }
     } else {
       // requested move is not possible to make
       return None;
     }
 
-    // guaranteed not in check if we are here
+    self.record_position();
+    self.update_state();
+    self.moves.push(san + self.check_suffix());
+    if let Some(clock) = &mut self.clock {
+      let press = clock.press(self.turn.opponent());
+      if let Some(undo) = self.undo_stack.last_mut() {
+        undo.clock_press = Some(press);
+      }
+    }
+
+    Some(self.state)
+  }
+
+  // moves the pieces and keeps what is needed to take the move back,
+  // but leaves the state, the history and the clock alone
+  pub(crate) fn move_pieces(
+    &mut self,
+    old_position: &Position,
+    new_position: &Position,
+    moving_piece: Piece,
+    promotion: Option<Piece>,
+  ) {
+    let en_passant_capture = match moving_piece {
+      Piece::Pawn(_) => {
+        Some(new_position.clone()) == self.en_passant && old_position.1 != new_position.1
+      }
+      _ => false,
+    };
+    // en passant takes the pawn beside the tile moved to
+    let captured_position = match en_passant_capture {
+      true => Position(old_position.0, new_position.1),
+      false => new_position.clone(),
+    };
+    let captured = self.board[captured_position.0][captured_position.1]
+      .map(|piece| (captured_position.clone(), piece));
+    let capture = captured.is_some();
+    self.hash ^= self.state_key();
+    self.undo_stack.push(Undo {
+      mv: Move::new(
+        Square::from_position(old_position),
+        Square::from_position(new_position),
+        promotion.map(|piece| piece.get_kind()),
+      ),
+      piece: moving_piece,
+      captured,
+      castling: self.castling.clone(),
+      en_passant: self.en_passant.clone(),
+      halfmove_clock: self.halfmove_clock,
+      fullmove_number: self.fullmove_number,
+      state: self.state,
+      draw_offer: self.draw_offer,
+      clock_press: None,
+    });
+
+    // the fifty-move count starts over on every capture and pawn move
+    match moving_piece {
+      Piece::Pawn(_) => self.halfmove_clock = 0,
+      _ if capture => self.halfmove_clock = 0,
+      _ => self.halfmove_clock += 1,
+    }
+    if self.turn == Colour::Black {
+      self.fullmove_number += 1;
+    }
+
+    self.set_piece(&captured_position, None);
+    self.set_piece(old_position, None);
+    self.set_piece(new_position, Some(promotion.unwrap_or(moving_piece)));
+    // castling, the king moves two tiles and the rook jumps over it
+    if let Piece::King(colour) = moving_piece {
+      let row = new_position.0;
+      if old_position.1 == 4 && new_position.1 == 6 {
+        self.set_piece(&Position(row, 7), None);
+        self.set_piece(&Position(row, 5), Some(Piece::Rook(colour)));
+      } else if old_position.1 == 4 && new_position.1 == 2 {
+        self.set_piece(&Position(row, 0), None);
+        self.set_piece(&Position(row, 3), Some(Piece::Rook(colour)));
+      }
+    }
+    // a double step leaves the skipped tile open for en passant on the next move only
+    self.en_passant = None;
+    if let Piece::Pawn(_) = moving_piece {
+      if (old_position.0 as i32 - new_position.0 as i32).abs() == 2 {
+        self.en_passant = Some(Position(
+          (old_position.0 + new_position.0) / 2,
+          old_position.1,
+        ));
+      }
+    }
+    self.update_castling_rights(old_position, new_position);
+    self.turn = self.turn.opponent();
+    self.hash ^= self.state_key();
+  }
+
+  // the board and the bitboards always change together, and so does the key
+  fn set_piece(&mut self, position: &Position, piece: Option<Piece>) {
+    let index = bitboard::index(position);
+    if let Some(old) = self.board[position.0][position.1] {
+      self.bitboards.toggle(index, old);
+      self.hash ^= zobrist::piece_key(old, index);
+    }
+    if let Some(new) = piece {
+      self.bitboards.toggle(index, new);
+      self.hash ^= zobrist::piece_key(new, index);
+    }
+    self.board[position.0][position.1] = piece;
+  }
+
+  fn update_state(&mut self) {
     self.state = GameState::InProgress;
 
     if self.turn == Colour::Black {
@@ -372,392 +605,199 @@ impl Game {
       if self.check(String::from("black")) {
         self.state = GameState::Check;
       }
-      // check blacks possible moves
-      // scan board for black pieces
-      // for every piece, check if there is a possible move
     } else {
       // check if white is now in check
       if self.check(String::from("white")) {
         self.state = GameState::Check;
       }
-      // check whites possible moves
-      // scan board for white pieces
-      // for every piece, check if there is a possible move
     }
 
+    // check for possibles moves here
+    // if none and in check -> checkmate
+    // if none and not in check -> stalemate
+    // checkmate on the last move still counts before the move rules
     let exists = self.move_exists();
     if !exists && self.state == GameState::Check {
       self.state = GameState::Checkmate;
     } else if !exists && self.state == GameState::InProgress {
       self.state = GameState::Stalemate;
-    }
-
-    // check for possibles moves here
-    // if none and in check -> checkmate
-    // if none and not in check -> stalemate
-    // if moves -> neither check- or stalemate
-
-    Some(self.state)
+    } else if self.insufficient_material() {
+      self.state = GameState::InsufficientMaterial;
+    } else if self.halfmove_clock >= 150 {
+      self.state = GameState::SeventyFiveMoveRule;
+    } else if self.get_repetition_count() >= 5 {
+      self.state = GameState::FivefoldRepetition;
+    // a check comes before a draw that can be claimed, see Game::can_claim_draw
+    } else if self.state != GameState::Check && self.halfmove_clock >= 100 {
+      self.state = GameState::FiftyMoveRule;
+    } else if self.state != GameState::Check && self.get_repetition_count() >= 3 {
+      self.state = GameState::ThreefoldRepetition;
+    }
+    self.update_outcome();
   }
 
-  fn move_exists(&self) -> bool {
+  fn insufficient_material(&self) -> bool {
+    // dead positions are kings with at most one minor piece between them,
+    // or kings with any number of bishops that all stand on the same colour
+    let mut knights = 0;
+    let mut bishops = vec![];
     for i in 0..=7 {
       for j in 0..=7 {
         match self.board[i][j] {
-          Some(piece) => {
-            if piece.get_colour() == self.turn {
-              let moves = self
-                .get_possible_moves(Game::parse_coordinates(Position(i, j)))
-                .unwrap();
-              if moves.len() != 0 {
-                return true;
-              }
-            }
-          }
-          None => {}
+          Some(Piece::King(_)) | None => {}
+          Some(Piece::Knight(_)) => knights += 1,
+          Some(Piece::Bishop(_)) => bishops.push((i + j) % 2),
+          Some(_) => return false,
         }
       }
     }
 
-    false
-  }
-
-  fn colour_from_string(s: &str) -> Colour {
-    if s == "white" {
-      Colour::White
-    } else {
-      Colour::Black
+    if knights + bishops.len() <= 1 {
+      return true;
     }
+    knights == 0 && bishops.iter().all(|colour| *colour == bishops[0])
   }
 
-  fn check(&self, possibly_checked_color: String) -> bool {
-    let king;
-    let c;
-    if possibly_checked_color == "white" {
-      king = self.get_king(String::from("white"));
-      c = Game::colour_from_string("black");
-
-      // SCAN FOR PAWN ATTACKS
-      // up left
-      if king.0 < 7 && king.1 > 0 {
-        if self.board[king.0 + 1][king.1 - 1] == Some(Piece::Pawn(c)) {
-          return true;
-        }
-      }
-      // up right
-      if king.0 < 7 && king.1 < 7 {
-        if self.board[king.0 + 1][king.1 + 1] == Some(Piece::Pawn(c)) {
-          return true;
-        }
-      }
-    } else {
-      king = self.get_king(String::from("black"));
-      c = Game::colour_from_string("white");
-      // scan for pawn attacks
-      // down left
-      if king.0 > 0 && king.1 > 0 {
-        if self.board[king.0 - 1][king.1 - 1] == Some(Piece::Pawn(c)) {
-          return true;
-        }
-      }
-      // down right
-      if king.0 > 0 && king.1 < 7 {
-        if self.board[king.0 - 1][king.1 + 1] == Some(Piece::Pawn(c)) {
-          return true;
-        }
-      }
-    }
-
-    let king_piece = self.board[king.0][king.1].unwrap();
-    // scan for vertical attacks above king
-    for row in (king.0 + 1)..=7 {
-      match self.board[row][king.1] {
-        Some(piece) => {
-          if piece == Piece::Rook(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-
-    // scan for vertical attacks below king
-    for row in (0..king.0).rev() {
-      match self.board[row][king.1] {
-        Some(piece) => {
-          if piece == Piece::Rook(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-
-    // scan for horizontal attacks right to the king
-    for col in (king.1 + 1)..=7 {
-      match self.board[king.0][col] {
-        Some(piece) => {
-          if piece == Piece::Rook(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-    // scan for horizontal attacks left to the king
-    for col in (0..king.1).rev() {
-      match self.board[king.0][col] {
-        Some(piece) => {
-          if piece == Piece::Rook(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-
-    // scan for top right diagonal attacks
-    let mut i = king.0;
-    let mut j = king.1;
-    loop {
-      if i == 7 || j == 7 {
-        break;
-      }
-      i += 1;
-      j += 1;
-
-      match self.board[i][j] {
-        Some(piece) => {
-          if piece == Piece::Bishop(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-
-    // scan for down right diagonal attacks
-    let mut i = king.0;
-    let mut j = king.1;
-    loop {
-      if i == 0 || j == 7 {
-        break;
-      }
-      i -= 1;
-      j += 1;
-
-      match self.board[i][j] {
-        Some(piece) => {
-          if piece == Piece::Bishop(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
+  fn record_position(&mut self) {
+    // the en passant tile only makes a position different if the capture can actually be made
+    let mut en_passant = None;
+    if let Some(target) = &self.en_passant {
+      let row = match self.turn {
+        Colour::White => 4,
+        Colour::Black => 3,
+      };
+      for col in [target.1 as i32 - 1, target.1 as i32 + 1].iter() {
+        if *col < 0 || *col > 7 || self.board[row][*col as usize] != Some(Piece::Pawn(self.turn)) {
+          continue;
         }
-        None => {}
-      }
-    }
-
-    // scan for top left diagonal attacks
-    let mut i = king.0;
-    let mut j = king.1;
-    loop {
-      if i == 7 || j == 0 {
-        break;
-      }
-      i += 1;
-      j -= 1;
-
-      match self.board[i][j] {
-        Some(piece) => {
-          if piece == Piece::Bishop(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
+        let from = Game::parse_coordinates(Position(row, *col as usize));
+        let to = Game::parse_coordinates(target.clone());
+        if self.get_possible_moves(from).unwrap().contains(&to) {
+          en_passant = Some(target.clone());
         }
-        None => {}
       }
     }
 
-    // scan for down left diagonal attacks
-    let mut i = king.0;
-    let mut j = king.1;
-    loop {
-      if i == 0 || j == 0 {
-        break;
-      }
-      i -= 1;
-      j -= 1;
-
-      match self.board[i][j] {
-        Some(piece) => {
-          if piece == Piece::Bishop(c) || piece == Piece::Queen(c) {
-            return true;
-          } else {
-            break;
-          }
-        }
-        None => {}
-      }
-    }
-    // scan for knight attacks
-    // top left fw move
-    if king.1 > 0 && king.0 < 6 {
-      match self.board[king.0 + 2][king.1 - 1] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
-    }
+    self.history.push(PositionRecord {
+      board: self.board,
+      turn: self.turn,
+      castling: self.castling.clone(),
+      en_passant,
+    });
+  }
 
-    // top right fw move
-    if king.1 < 7 && king.0 < 6 {
-      match self.board[king.0 + 2][king.1 + 1] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
-    }
+  pub fn get_repetition_count(&self) -> usize {
+    // positions before the last capture or pawn move can never come back
+    let current = match self.history.last() {
+      Some(record) => record,
+      None => return 0,
+    };
+    self
+      .history
+      .iter()
+      .rev()
+      .take(self.halfmove_clock as usize + 1)
+      .filter(|record| *record == current)
+      .count()
+  }
 
-    // left side top move
-    if king.1 > 1 && king.0 < 7 {
-      match self.board[king.0 + 1][king.1 - 2] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
-    }
+  fn is_game_over(&self) -> bool {
+    matches!(
+      self.state,
+      GameState::Checkmate
+        | GameState::Stalemate
+        | GameState::SeventyFiveMoveRule
+        | GameState::FivefoldRepetition
+        | GameState::InsufficientMaterial
+        | GameState::GameOver
+    )
+  }
 
-    // left side down move
-    if king.1 > 1 && king.0 > 0 {
-      match self.board[king.0 - 1][king.1 - 2] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
+  pub fn claim_draw(&mut self) -> bool {
+    match self.claimable_draw() {
+      Some(termination) => {
+        self.state = GameState::GameOver;
+        self.outcome = Some((GameResult::Draw, termination));
+        true
       }
+      None => false,
     }
+  }
 
-    // down left move
-    if king.1 > 0 && king.0 > 1 {
-      match self.board[king.0 - 2][king.1 - 1] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
-    }
+  // also when the state is Check, which hides the draw
+  pub fn can_claim_draw(&self) -> bool {
+    self.claimable_draw().is_some()
+  }
 
-    // down right move
-    if king.1 < 7 && king.0 > 1 {
-      match self.board[king.0 - 2][king.1 + 1] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
+  fn claimable_draw(&self) -> Option<Termination> {
+    if self.is_game_over() {
+      None
+    } else if self.halfmove_clock >= 100 {
+      Some(Termination::FiftyMoveRule)
+    } else if self.get_repetition_count() >= 3 {
+      Some(Termination::Repetition)
+    } else {
+      None
     }
+  }
 
-    // right down move
-    if king.1 < 6 && king.0 > 0 {
-      match self.board[king.0 - 1][king.1 + 2] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
+  fn update_castling_rights(&mut self, from: &Position, to: &Position) {
+    // a king that moves loses both rights, a rook that moves or gets captured loses its own
+    for pos in [from, to].iter() {
+      match (pos.0, pos.1) {
+        (0, 4) => {
+          self.castling.white_king = false;
+          self.castling.white_queen = false;
+        }
+        (7, 4) => {
+          self.castling.black_king = false;
+          self.castling.black_queen = false;
+        }
+        (0, 0) => self.castling.white_queen = false,
+        (0, 7) => self.castling.white_king = false,
+        (7, 0) => self.castling.black_queen = false,
+        (7, 7) => self.castling.black_king = false,
+        _ => {}
       }
     }
+  }
 
-    // right up move
-    if king.1 < 6 && king.0 < 7 {
-      match self.board[king.0 + 1][king.1 + 2] {
-        Some(piece) => {
-          if piece.get_colour() != king_piece.get_colour() && piece == Piece::Knight(c) {
-            return true;
-          }
-        }
-        None => {}
-      }
-    }
-    false
+  fn move_exists(&self) -> bool {
+    bitboard::tiles(self.bitboards.get_colour(self.turn)).any(|index| {
+      let from = bitboard::position(index);
+      let piece = self.board[from.0][from.1].unwrap();
+      self
+        .get_all_moves(from.clone(), piece)
+        .iter()
+        .any(|to| self.not_in_check_after_move(&from, to, piece))
+    })
   }
 
-  fn get_king(&self, colour: String) -> Position {
-    for i in 0..=7 {
-      for j in 0..=7 {
-        match self.board[i][j] {
-          Some(piece) => match piece {
-            Piece::King(Colour::White) => {
-              if colour == "white" {
-                return Position(i, j);
-              }
-            }
-            Piece::King(Colour::Black) => {
-              if colour == "black" {
-                return Position(i, j);
-              }
-            }
-            _ => {}
-          },
-          _ => {}
-        }
-      }
+  fn colour_from_string(s: &str) -> Colour {
+    if s == "white" {
+      Colour::White
+    } else {
+      Colour::Black
     }
-    // will never happen, fix error handling
-    Position(0, 0)
   }
 
-  fn parse_string(position: &String) -> Position {
-    // turn string into two chars
-    // match the first one with a num
-    // parse the other one - 1 to a int
-    let mut chars = vec![];
-    for ch in position.chars() {
-      chars.push(ch);
-    }
+  fn check(&self, possibly_checked_color: String) -> bool {
+    let colour = Game::colour_from_string(&possibly_checked_color);
+    let king = self.get_king(possibly_checked_color);
+    self.is_attacked(&king, colour.opponent())
+  }
 
-    let col = match chars[0] {
-      'a' => 0,
-      'b' => 1,
-      'c' => 2,
-      'd' => 3,
-      'e' => 4,
-      'f' => 5,
-      'g' => 6,
-      'h' => 7,
-      _ => 8, // handle error
-    };
-    let row = chars[1].to_digit(10).unwrap() - 1; // handle error
+  fn is_attacked(&self, square: &Position, c: Colour) -> bool {
+    let occupied = self.bitboards.occupied();
+    self
+      .bitboards
+      .attackers(bitboard::index(square), c, occupied)
+      != 0
+  }
 
-    return Position(row as usize, col);
+  fn get_king(&self, colour: String) -> Position {
+    let colour = Game::colour_from_string(&colour);
+    bitboard::position(self.bitboards.get_king(colour))
   }
 
   fn parse_coordinates(position: Position) -> String {
@@ -778,470 +818,120 @@ impl Game {
     fin
   }
 
-  pub fn set_promotion(&mut self, position: String, new_piece: char) -> () {
-    if !self.promote.0 {
-      return;
-    }
-    let piece = self.get_piece_at(position.clone()).unwrap();
-    let pos = Game::parse_string(&position);
-    match new_piece {
-      'q' => self.board[pos.0][pos.1] = Some(Piece::Queen(piece.get_colour())),
-      'r' => self.board[pos.0][pos.1] = Some(Piece::Rook(piece.get_colour())),
-      'b' => self.board[pos.0][pos.1] = Some(Piece::Bishop(piece.get_colour())),
-      'k' => self.board[pos.0][pos.1] = Some(Piece::Knight(piece.get_colour())),
-      _ => {}
-    }
-  }
-
   pub fn get_game_state(&self) -> &GameState {
     &self.state
   }
 
-  fn not_in_check_after_move(&self, from: &Position, to: &Position, moving_piece: Piece) -> bool {
-    // 1. make move
-    // clone board
-    let mut fake_game = self.clone();
-    fake_game.board[to.0][to.1] = Some(moving_piece);
-    fake_game.board[from.0][from.1] = None;
-    if moving_piece.get_colour() == Colour::White {
-      // check if white is still in check
-      if fake_game.check(String::from("white")) {
-        fake_game.state = GameState::Check;
-        return false;
-      }
-    } else {
-      // check if black is still in check
-      if fake_game.check(String::from("black")) {
-        fake_game.state = GameState::Check;
-        return false;
-      }
-    }
-    true
+  pub fn get_halfmove_clock(&self) -> u32 {
+    self.halfmove_clock
   }
 
-  pub fn get_possible_moves(&self, _position: String) -> Option<Vec<String>> {
-    let position = Game::parse_string(&_position);
-    let moving_piece = self.board[position.0][position.1]?;
-    let mut all_moves = Game::get_all_moves(self, position, moving_piece);
-    let from = Game::parse_string(&_position);
-    all_moves.retain(|to| self.not_in_check_after_move(&from, to, moving_piece));
-
-    let mut str_moves = vec![];
-    for mv in all_moves {
-      str_moves.push(Game::parse_coordinates(mv));
-    }
-
-    Some(str_moves)
+  pub fn get_fullmove_number(&self) -> u32 {
+    self.fullmove_number
   }
 
-  fn get_all_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let moves = match piece {
-      Piece::Pawn(_) => self.get_pawn_moves(position, piece),
-      Piece::Rook(_) => self.get_rook_moves(position, piece),
-      Piece::Knight(_) => self.get_knight_moves(position, piece),
-      Piece::Bishop(_) => self.get_bishop_moves(position, piece),
-      Piece::Queen(_) => self.get_queen_moves(position, piece),
-      Piece::King(_) => self.get_king_moves(position, piece),
-    };
-
-    moves
+  pub fn get_en_passant_target(&self) -> Option<String> {
+    self.en_passant.clone().map(Game::parse_coordinates)
   }
 
-  fn get_pawn_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let mut moves = vec![];
-
-    match piece.get_colour() {
-      Colour::White => {
-        // 2 forward
-        if position.0 == 1 {
-          if self.board[3][position.1].is_none() && self.board[2][position.1].is_none() {
-            moves.push(Position(3, position.1));
-          }
-        }
-
-        // 1 forward
-        if position.0 < 7 {
-          if self.board[position.0 + 1][position.1].is_none() {
-            moves.push(Position(position.0 + 1, position.1));
-          }
-        }
-
-        // 1 diagonal to the right
-        if position.1 < 7 {
-          match self.board[position.0 + 1][position.1 + 1] {
-            Some(attacked_piece) => {
-              if attacked_piece.get_colour() == Colour::Black {
-                moves.push(Position(position.0 + 1, position.1 + 1));
-              }
-            }
-            None => {}
-          }
-        }
-
-        // 1 diagonal to the left
-        if position.1 > 0 {
-          match self.board[position.0 + 1][position.1 - 1] {
-            Some(attacked_piece) => {
-              if attacked_piece.get_colour() == Colour::Black {
-                moves.push(Position(position.0 + 1, position.1 - 1));
-              }
-            }
-            None => {}
-          }
-        }
-      }
-      Colour::Black => {
-        // 2fw
-        if position.0 == 6 {
-          if self.board[4][position.1].is_none() && self.board[5][position.1].is_none() {
-            moves.push(Position(4, position.1));
-          }
-        }
-
-        // 1 forward
-        if position.0 > 0 {
-          if self.board[position.0 - 1][position.1].is_none() {
-            moves.push(Position(position.0 - 1, position.1));
-          }
-        }
-
-        // 1 diagonal to the right
-        if position.1 < 7 {
-          match self.board[position.0 - 1][position.1 + 1] {
-            Some(attacked_piece) => {
-              if attacked_piece.get_colour() == Colour::White {
-                moves.push(Position(position.0 - 1, position.1 + 1));
-              }
-            }
-            None => {}
-          }
-        }
-
-        // 1 diagonal to the left
-        if position.1 > 0 {
-          match self.board[position.0 - 1][position.1 - 1] {
-            Some(attacked_piece) => {
-              if attacked_piece.get_colour() == Colour::White {
-                moves.push(Position(position.0 - 1, position.1 - 1));
-              }
-            }
-            None => {}
-          }
-        }
-      }
-    }
-
-    moves
-  }
-
-  fn get_rook_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    self.up_down_slides(position, piece)
+  pub fn get_en_passant_square(&self) -> Option<Square> {
+    self.en_passant.as_ref().map(Square::from_position)
   }
 
-  fn get_knight_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let mut moves = vec![];
-    // maximalt 8 moves
-    // check all 8
-    // for every check, check bounds, and attacked piece
-
-    // top left fw move
-    if position.1 > 0 && position.0 < 6 {
-      if Game::is_legal(self.board[position.0 + 2][position.1 - 1], piece) {
-        moves.push(Position(position.0 + 2, position.1 - 1));
-      }
-    }
-
-    // top right fw move
-    if position.1 < 7 && position.0 < 6 {
-      if Game::is_legal(self.board[position.0 + 2][position.1 + 1], piece) {
-        moves.push(Position(position.0 + 2, position.1 + 1));
-      }
-    }
-
-    // left side top move
-    if position.1 > 1 && position.0 < 7 {
-      if Game::is_legal(self.board[position.0 + 1][position.1 - 2], piece) {
-        moves.push(Position(position.0 + 1, position.1 - 2));
-      }
-    }
-
-    // left side down move
-    if position.1 > 1 && position.0 > 0 {
-      if Game::is_legal(self.board[position.0 - 1][position.1 - 2], piece) {
-        moves.push(Position(position.0 - 1, position.1 - 2));
-      }
-    }
-
-    // down left move
-    if position.1 > 0 && position.0 > 1 {
-      if Game::is_legal(self.board[position.0 - 2][position.1 - 1], piece) {
-        moves.push(Position(position.0 - 2, position.1 - 1));
-      }
-    }
-
-    // down right move
-    if position.1 < 7 && position.0 > 1 {
-      if Game::is_legal(self.board[position.0 - 2][position.1 + 1], piece) {
-        moves.push(Position(position.0 - 2, position.1 + 1));
-      }
-    }
-
-    // right down move
-    if position.1 < 6 && position.0 > 0 {
-      if Game::is_legal(self.board[position.0 - 1][position.1 + 2], piece) {
-        moves.push(Position(position.0 - 1, position.1 + 2));
-      }
-    }
-
-    // right up move
-    if position.1 < 6 && position.0 < 7 {
-      if Game::is_legal(self.board[position.0 + 1][position.1 + 2], piece) {
-        moves.push(Position(position.0 + 1, position.1 + 2));
-      }
+  fn not_in_check_after_move(&self, from: &Position, to: &Position, moving_piece: Piece) -> bool {
+    // make the move on a copy of the bitboards, the board itself is not needed
+    let mut bitboards = self.bitboards;
+    // an en passant capture removes a pawn from a third tile, which can expose the king
+    let captured = match moving_piece {
+      Piece::Pawn(_) if Some(to.clone()) == self.en_passant && from.1 != to.1 => {
+        Position(from.0, to.1)
+      }
+      _ => to.clone(),
+    };
+    if let Some(piece) = self.board[captured.0][captured.1] {
+      bitboards.toggle(bitboard::index(&captured), piece);
     }
+    bitboards.toggle(bitboard::index(from), moving_piece);
+    bitboards.toggle(bitboard::index(to), moving_piece);
 
-    moves
+    let colour = moving_piece.get_colour();
+    let king = bitboards.get_king(colour);
+    bitboards.attackers(king, colour.opponent(), bitboards.occupied()) == 0
   }
 
-  fn get_bishop_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    self.diagonal_slides(position, piece)
-  }
+  pub fn get_possible_moves(&self, _position: String) -> Option<Vec<String>> {
+    let position = Game::parse_tile(&_position)?;
+    let moving_piece = self.board[position.0][position.1]?;
+    let moves = self.get_legal_targets(&position, moving_piece);
 
-  fn get_queen_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let mut diagonal_moves = self.diagonal_slides(position.clone(), piece);
-    let mut up_down_moves = self.up_down_slides(position.clone(), piece);
+    let mut str_moves = vec![];
+    for mv in moves {
+      str_moves.push(Game::parse_coordinates(mv));
+    }
 
-    diagonal_moves.append(&mut up_down_moves);
-    diagonal_moves
+    Some(str_moves)
   }
 
-  fn get_king_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let mut moves = vec![];
-    // left
-    if position.1 > 0 {
-      if Game::is_legal(self.board[position.0][position.1 - 1], piece) {
-        moves.push(Position(position.0, position.1 - 1));
-      }
-    }
-    // top left
-    if position.0 < 7 && position.1 > 0 {
-      if Game::is_legal(self.board[position.0 + 1][position.1 - 1], piece) {
-        moves.push(Position(position.0 + 1, position.1 - 1));
-      }
-    }
-    // top
-    if position.0 < 7 {
-      if Game::is_legal(self.board[position.0 + 1][position.1], piece) {
-        moves.push(Position(position.0 + 1, position.1));
-      }
-    }
-    // top right
-    if position.0 < 7 && position.1 < 7 {
-      if Game::is_legal(self.board[position.0 + 1][position.1 + 1], piece) {
-        moves.push(Position(position.0 + 1, position.1 + 1));
-      }
-    }
-    // right
-    if position.1 < 7 {
-      if Game::is_legal(self.board[position.0][position.1 + 1], piece) {
-        moves.push(Position(position.0, position.1 + 1));
-      }
-    }
-    // down right
-    if position.0 > 0 && position.1 < 7 {
-      if Game::is_legal(self.board[position.0 - 1][position.1 + 1], piece) {
-        moves.push(Position(position.0 - 1, position.1 + 1));
-      }
-    }
-    // down
-    if position.0 > 0 {
-      if Game::is_legal(self.board[position.0 - 1][position.1], piece) {
-        moves.push(Position(position.0 - 1, position.1));
-      }
-    }
-    // down left
-    if position.0 > 0 && position.1 > 0 {
-      if Game::is_legal(self.board[position.0 - 1][position.1 - 1], piece) {
-        moves.push(Position(position.0 - 1, position.1 - 1));
-      }
-    }
+  // the tiles the piece can move to without leaving its own king in check
+  pub(crate) fn get_legal_targets(&self, position: &Position, piece: Piece) -> Vec<Position> {
+    let mut moves = self.get_all_moves(position.clone(), piece);
+    moves.retain(|to| self.not_in_check_after_move(position, to, piece));
     moves
   }
 
-  fn diagonal_slides(&self, position: Position, piece: Piece) -> Vec<Position> {
-    // bottom left
-    let mut moves = vec![];
-    if position.0 > 0 && position.1 > 0 {
-      let mut i = position.0;
-      let mut j = position.1;
-      loop {
-        i -= 1;
-        j -= 1;
-
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][j], piece);
-        if should_add {
-          moves.push(Position(i, j));
-        }
-        if should_break {
-          break;
-        }
-
-        if i == 0 || j == 0 {
-          break;
-        }
-      }
-    }
-
-    // top left
-    if position.0 < 7 && position.1 > 0 {
-      let mut i = position.0;
-      let mut j = position.1;
-      loop {
-        i += 1;
-        j -= 1;
-
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][j], piece);
-        if should_add {
-          moves.push(Position(i, j));
-        }
-        if should_break {
-          break;
-        }
-
-        if i == 7 || j == 0 {
-          break;
-        }
-      }
-    }
-
-    // top right
-    if position.0 < 7 && position.1 < 7 {
-      let mut i = position.0;
-      let mut j = position.1;
-      loop {
-        i += 1;
-        j += 1;
-
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][j], piece);
-        if should_add {
-          moves.push(Position(i, j));
-        }
-        if should_break {
-          break;
-        }
-
-        if i == 7 || j == 7 {
-          break;
-        }
+  // the tiles the piece could move to if its own king didn't matter
+  fn get_all_moves(&self, position: Position, piece: Piece) -> Vec<Position> {
+    let index = bitboard::index(&position);
+    let colour = piece.get_colour();
+    let occupied = self.bitboards.occupied();
+    let targets = match piece {
+      Piece::Pawn(_) => {
+        // only a pawn on the row beside the target can take en passant
+        let en_passant = match (&self.en_passant, colour) {
+          (Some(target), Colour::White) if target.0 == 5 => 1 << bitboard::index(target),
+          (Some(target), Colour::Black) if target.0 == 2 => 1 << bitboard::index(target),
+          _ => 0,
+        };
+        let enemies = self.bitboards.get_colour(colour.opponent()) | en_passant;
+        bitboard::pawn_pushes(colour, index, occupied)
+          | (bitboard::pawn_attacks(colour, index) & enemies)
       }
-    }
-
-    // bottom right
-    if position.0 > 0 && position.1 < 7 {
-      let mut i = position.0;
-      let mut j = position.1;
-      loop {
-        i -= 1;
-        j += 1;
-
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][j], piece);
-        if should_add {
-          moves.push(Position(i, j));
-        }
-        if should_break {
-          break;
-        }
+      _ => bitboard::attacks(piece, index, occupied) & !self.bitboards.get_colour(colour),
+    };
 
-        if i == 0 || j == 7 {
-          break;
-        }
-      }
+    let mut moves: Vec<Position> = bitboard::tiles(targets).map(bitboard::position).collect();
+    if let Piece::King(_) = piece {
+      self.add_castling_moves(position, colour, &mut moves);
     }
-
     moves
   }
 
-  fn up_down_slides(&self, position: Position, piece: Piece) -> Vec<Position> {
-    let mut moves = vec![];
-    // every tile forward (whites perspective)
-    if position.0 < 7 {
-      for i in (position.0 + 1)..=7 {
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][position.1], piece);
-        if should_add {
-          moves.push(Position(i, position.1));
-        }
-        if should_break {
-          break;
-        }
-      }
-    }
-    // every tile backward
-    if position.0 > 0 {
-      for i in (0..=position.0 - 1).rev() {
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[i][position.1], piece);
-        if should_add {
-          moves.push(Position(i, position.1));
-        }
-        if should_break {
-          break;
-        }
-      }
-    }
-    // every tile to right
-    if position.1 < 7 {
-      for i in (position.1 + 1)..=7 {
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[position.0][i], piece);
-        if should_add {
-          moves.push(Position(position.0, i));
-        }
-        if should_break {
-          break;
-        }
-      }
-    }
-    // every tile to left
-    if position.1 > 0 {
-      for i in (0..=(position.1 - 1)).rev() {
-        let (should_add, should_break) = Game::is_legal_in_loop(self.board[position.0][i], piece);
-        if should_add {
-          moves.push(Position(position.0, i));
-        }
-        if should_break {
-          break;
-        }
-      }
-    }
-
-    moves
-  }
-
-  fn is_legal(attacked_position: Option<Piece>, piece: Piece) -> bool {
-    match attacked_position {
-      Some(attacked_piece) => {
-        if attacked_piece.get_colour() != piece.get_colour() {
-          true
-        } else {
-          false
-        }
-      }
-      None => true,
-    }
-  }
-
-  fn is_legal_in_loop(attacked_position: Option<Piece>, piece: Piece) -> (bool, bool) {
-    let mut should_break = false;
-    let mut should_add = false;
-
-    match attacked_position {
-      Some(attacked_piece) => {
-        if attacked_piece.get_colour() != piece.get_colour() {
-          should_add = true;
-        }
-        should_break = true;
+  fn add_castling_moves(&self, position: Position, colour: Colour, moves: &mut Vec<Position>) {
+    // castling, the king can't castle out of, through or into check
+    let (row, king_side, queen_side) = match colour {
+      Colour::White => (0, self.castling.white_king, self.castling.white_queen),
+      Colour::Black => (7, self.castling.black_king, self.castling.black_queen),
+    };
+    if position == Position(row, 4) && !self.is_attacked(&position, colour.opponent()) {
+      if king_side
+        && self.board[row][7] == Some(Piece::Rook(colour))
+        && self.board[row][5].is_none()
+        && self.board[row][6].is_none()
+        && !self.is_attacked(&Position(row, 5), colour.opponent())
+        && !self.is_attacked(&Position(row, 6), colour.opponent())
+      {
+        moves.push(Position(row, 6));
+      }
+      if queen_side
+        && self.board[row][0] == Some(Piece::Rook(colour))
+        && self.board[row][1].is_none()
+        && self.board[row][2].is_none()
+        && self.board[row][3].is_none()
+        && !self.is_attacked(&Position(row, 3), colour.opponent())
+        && !self.is_attacked(&Position(row, 2), colour.opponent())
+      {
+        moves.push(Position(row, 2));
       }
-      None => should_add = true,
     }
-
-    (should_add, should_break)
   }
 }
@@ -1,5 +1,45 @@
-use alholmbe_chess::Game;
+use alholmbe_chess::{uci, xboard, Game};
+use std::env;
+use std::process;
 
 fn main() {
-  Game::play();
+  let args: Vec<String> = env::args().skip(1).collect();
+  match args.first().map(|arg| arg.as_str()) {
+    Some("perft") => perft(&args[1..]),
+    Some("--uci") => uci(),
+    Some("--xboard") => xboard(),
+    _ => Game::play(),
+  }
+}
+
+// perft <depth> [fen], prints the count after every move and the total
+fn perft(args: &[String]) {
+  let depth: u32 = match args.first().and_then(|depth| depth.parse().ok()) {
+    Some(depth) => depth,
+    None => {
+      eprintln!("usage: perft <depth> [fen]");
+      process::exit(1);
+    }
+  };
+  let game = match args.len() {
+    1 => Game::new(),
+    _ => match Game::try_from_fen(&args[1..].join(" ")) {
+      Ok(game) => game,
+      Err(err) => {
+        eprintln!("invalid FEN string: {}", err);
+        process::exit(1);
+      }
+    },
+  };
+
+  let counts = game.divide(depth);
+  for (mv, count) in counts.iter() {
+    println!("{}: {}", mv, count);
+  }
+  // there are no moves to list at depth 0, where the position is the one node
+  let nodes = match depth {
+    0 => game.perft(0),
+    _ => counts.iter().map(|(_, count)| count).sum(),
+  };
+  println!("\nNodes searched: {}", nodes);
 }
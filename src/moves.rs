@@ -0,0 +1,186 @@
+use crate::bitboard;
+use crate::{Colour, Game, GameState, Piece, Square};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PieceKind {
+  King,
+  Rook,
+  Bishop,
+  Queen,
+  Knight,
+  Pawn,
+}
+
+impl PieceKind {
+  pub(crate) fn with_colour(self, colour: Colour) -> Piece {
+    match self {
+      PieceKind::King => Piece::King(colour),
+      PieceKind::Rook => Piece::Rook(colour),
+      PieceKind::Bishop => Piece::Bishop(colour),
+      PieceKind::Queen => Piece::Queen(colour),
+      PieceKind::Knight => Piece::Knight(colour),
+      PieceKind::Pawn => Piece::Pawn(colour),
+    }
+  }
+}
+
+impl Piece {
+  pub fn get_kind(&self) -> PieceKind {
+    match self {
+      Piece::King(_) => PieceKind::King,
+      Piece::Rook(_) => PieceKind::Rook,
+      Piece::Bishop(_) => PieceKind::Bishop,
+      Piece::Queen(_) => PieceKind::Queen,
+      Piece::Knight(_) => PieceKind::Knight,
+      Piece::Pawn(_) => PieceKind::Pawn,
+    }
+  }
+}
+
+// what else happens with a move, filled in by Game::get_legal_moves and not needed to make it
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MoveFlags {
+  pub capture: bool,
+  pub en_passant: bool,
+  pub castling: bool,
+  pub double_step: bool,
+}
+
+// a move as the tiles it goes between, and the piece a pawn is promoted to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Move {
+  pub from: Square,
+  pub to: Square,
+  pub promotion: Option<PieceKind>,
+  pub flags: MoveFlags,
+}
+
+impl Move {
+  // long algebraic notation as used by UCI, like "e2e4", "e1g1" or "e7e8q"
+  pub fn from_uci(uci: &str) -> Option<Move> {
+    if !uci.is_ascii() || (uci.len() != 4 && uci.len() != 5) {
+      return None;
+    }
+    let from = Square::from_name(&uci[0..2])?;
+    let to = Square::from_name(&uci[2..4])?;
+    let promotion = match &uci[4..] {
+      "" => None,
+      "q" => Some(PieceKind::Queen),
+      "r" => Some(PieceKind::Rook),
+      "b" => Some(PieceKind::Bishop),
+      "n" => Some(PieceKind::Knight),
+      _ => return None,
+    };
+    Some(Move::new(from, to, promotion))
+  }
+
+  pub fn new(from: Square, to: Square, promotion: Option<PieceKind>) -> Move {
+    Move {
+      from,
+      to,
+      promotion,
+      flags: MoveFlags::default(),
+    }
+  }
+}
+
+impl fmt::Display for Move {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let promotion = match self.promotion {
+      Some(kind) => kind.with_colour(Colour::Black).to_fen().to_string(),
+      None => String::new(),
+    };
+    write!(f, "{}{}{}", self.from, self.to, promotion)
+  }
+}
+
+impl Game {
+  pub fn make_uci_move(&mut self, uci: &str) -> Option<GameState> {
+    let mv = Move::from_uci(uci)?;
+    self.apply_move(&mv)
+  }
+
+  // a move as a person or a GUI types it, the tiles with the promotion along with them
+  // like e7e8q, and anything else is read as SAN
+  pub(crate) fn make_typed_move(&mut self, input: &str) -> Option<GameState> {
+    self
+      .make_uci_move(input)
+      .or_else(|| self.make_san_move(input))
+  }
+
+  // makes the move and its promotion in one go, nothing changes if either is wrong
+  pub fn apply_move(&mut self, mv: &Move) -> Option<GameState> {
+    let from = mv.from.to_position();
+    let piece = self.board[from.0][from.1]?;
+    let promotes =
+      piece.get_kind() == PieceKind::Pawn && (mv.to.get_rank() == 0 || mv.to.get_rank() == 7);
+    // unlike make_move there is no queen by default
+    if promotes && mv.promotion.is_none() {
+      return None;
+    }
+    self.make_move_promoting(mv.from.to_string(), mv.to.to_string(), mv.promotion)
+  }
+
+  // every legal move for the piece on the tile, a promotion is one move for each piece
+  pub fn get_legal_moves(&self, from: Square) -> Vec<Move> {
+    let position = from.to_position();
+    let piece = match self.board[position.0][position.1] {
+      Some(piece) => piece,
+      None => return vec![],
+    };
+    let targets = self.get_legal_targets(&position, piece);
+
+    let mut moves = vec![];
+    for target in targets {
+      let is_pawn = piece.get_kind() == PieceKind::Pawn;
+      let en_passant = is_pawn && Some(target.clone()) == self.en_passant && position.1 != target.1;
+      let flags = MoveFlags {
+        capture: self.board[target.0][target.1].is_some() || en_passant,
+        en_passant,
+        castling: piece.get_kind() == PieceKind::King
+          && (position.1 as i32 - target.1 as i32).abs() == 2,
+        double_step: is_pawn && (position.0 as i32 - target.0 as i32).abs() == 2,
+      };
+      let promotions = match (is_pawn, target.0) {
+        (true, 0) | (true, 7) => vec![
+          Some(PieceKind::Queen),
+          Some(PieceKind::Rook),
+          Some(PieceKind::Bishop),
+          Some(PieceKind::Knight),
+        ],
+        _ => vec![None],
+      };
+      for promotion in promotions {
+        moves.push(Move {
+          from,
+          to: Square::from_position(&target),
+          promotion,
+          flags,
+        });
+      }
+    }
+    moves
+  }
+
+  // every legal move for the side to move
+  pub fn get_all_legal_moves(&self) -> Vec<Move> {
+    bitboard::tiles(self.bitboards.get_colour(self.turn))
+      .flat_map(|index| self.get_legal_moves(Square::from_position(&bitboard::position(index))))
+      .collect()
+  }
+
+  // only moves the pieces, for looking ahead where the rest of the game doesn't matter
+  pub(crate) fn make_move_quietly(&mut self, mv: &Move) {
+    let from = mv.from.to_position();
+    let piece = self.board[from.0][from.1].expect("the move is legal");
+    let promotion = mv.promotion.map(|kind| kind.with_colour(self.turn));
+    self.move_pieces(&from, &mv.to.to_position(), piece, promotion);
+  }
+
+  pub(crate) fn undo_quietly(&mut self) {
+    if let Some(undo) = self.undo_stack.pop() {
+      self.unmove_pieces(&undo);
+    }
+  }
+}
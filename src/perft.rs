@@ -0,0 +1,40 @@
+use crate::{Game, Move};
+
+impl Game {
+  // the number of move sequences of the given length, for checking the move generation
+  pub fn perft(&self, depth: u32) -> u64 {
+    self.clone().count_nodes(depth)
+  }
+
+  // the perft count after each legal move, to find where a wrong count comes from
+  pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+      return vec![];
+    }
+    let mut game = self.clone();
+    let mut counts = vec![];
+    for mv in game.get_all_legal_moves() {
+      game.make_move_quietly(&mv);
+      counts.push((mv, game.count_nodes(depth - 1)));
+      game.undo_quietly();
+    }
+    counts
+  }
+
+  fn count_nodes(&mut self, depth: u32) -> u64 {
+    if depth == 0 {
+      return 1;
+    }
+    let moves = self.get_all_legal_moves();
+    if depth == 1 {
+      return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for mv in moves.iter() {
+      self.make_move_quietly(mv);
+      nodes += self.count_nodes(depth - 1);
+      self.undo_quietly();
+    }
+    nodes
+  }
+}
@@ -0,0 +1,268 @@
+use crate::{Colour, FenError, Game, GameResult, GameState, Termination, STARTING_FEN};
+use std::fmt;
+
+// the tags every PGN document has, in the order they have to come in
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+  ("Event", "?"),
+  ("Site", "?"),
+  ("Date", "????.??.??"),
+  ("Round", "?"),
+  ("White", "?"),
+  ("Black", "?"),
+  ("Result", "*"),
+];
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PgnError {
+  // a line in the tag section that isn't a [Name "value"] pair
+  InvalidTag(String),
+  InvalidFen(FenError),
+  // a '{' comment that is never closed
+  UnterminatedComment,
+  // a ')' without a '(' or a variation that is never closed
+  UnbalancedVariation,
+  // plies are counted from 1 at the first move of the movetext
+  IllegalMove { ply: usize, san: String },
+}
+
+impl fmt::Display for PgnError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PgnError::InvalidTag(line) => write!(f, "invalid tag pair: {}", line),
+      PgnError::InvalidFen(err) => write!(f, "invalid FEN tag, {}", err),
+      PgnError::UnterminatedComment => write!(f, "a comment is never closed"),
+      PgnError::UnbalancedVariation => write!(f, "the variation parentheses don't match"),
+      PgnError::IllegalMove { ply, san } => {
+        write!(f, "illegal move \"{}\" at ply {}", san, ply)
+      }
+    }
+  }
+}
+
+impl std::error::Error for PgnError {}
+
+impl Game {
+  // reads the first game in the PGN and replays its mainline, variations are skipped
+  pub fn from_pgn(pgn: &str) -> Result<Game, PgnError> {
+    let mut tags = vec![];
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+      let trimmed = line.trim();
+      // lines starting with '%' are escaped and not part of the game
+      if trimmed.starts_with('%') {
+        continue;
+      }
+      if movetext.trim().is_empty() && trimmed.starts_with('[') {
+        tags.push(Game::parse_tag_pair(trimmed)?);
+      } else {
+        movetext.push_str(line);
+        movetext.push('\n');
+      }
+    }
+
+    let mut game = match tags.iter().find(|(name, _)| name == "FEN") {
+      Some((_, fen)) => Game::try_from_fen(fen).map_err(PgnError::InvalidFen)?,
+      None => Game::new(),
+    };
+    for (name, value) in tags.iter() {
+      if name != "SetUp" && name != "FEN" {
+        game.set_pgn_tag(name, value);
+      }
+    }
+
+    let mut chars = movetext.chars().peekable();
+    let mut depth = 0;
+    let mut ply = 0;
+    while let Some(c) = chars.next() {
+      match c {
+        '{' => {
+          if !chars.any(|c| c == '}') {
+            return Err(PgnError::UnterminatedComment);
+          }
+        }
+        // the rest of the line is a comment
+        ';' => {
+          chars.any(|c| c == '\n');
+        }
+        '(' => depth += 1,
+        ')' if depth == 0 => return Err(PgnError::UnbalancedVariation),
+        ')' => depth -= 1,
+        _ if c.is_whitespace() => {}
+        _ => {
+          let mut token = c.to_string();
+          while let Some(&next) = chars.peek() {
+            if next.is_whitespace() || "{}();".contains(next) {
+              break;
+            }
+            token.push(next);
+            chars.next();
+          }
+          // annotation glyphs and moves in variations don't change the mainline
+          if token.starts_with('$') || depth > 0 {
+            continue;
+          }
+          if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str()) {
+            let termination = tags.iter().find(|(name, _)| name == "Termination");
+            game.set_imported_result(&token, termination.map(|(_, value)| value.as_str()));
+            break;
+          }
+          // the move number can be written right before the move, as in "1.e4"
+          let san = match token.rfind('.') {
+            Some(i) if token.chars().next().unwrap().is_ascii_digit() => &token[i + 1..],
+            _ => &token[..],
+          };
+          if san.is_empty() {
+            continue;
+          }
+          ply += 1;
+          game
+            .make_san_move(san)
+            .ok_or_else(|| PgnError::IllegalMove {
+              ply,
+              san: String::from(san),
+            })?;
+        }
+      }
+    }
+    if depth > 0 {
+      return Err(PgnError::UnbalancedVariation);
+    }
+
+    Ok(game)
+  }
+
+  // the result token ends the game when the board didn't, like after a resignation,
+  // with the Termination tag telling a loss on time apart
+  fn set_imported_result(&mut self, token: &str, termination: Option<&str>) {
+    let result = match token {
+      "1-0" => GameResult::WhiteWins,
+      "0-1" => GameResult::BlackWins,
+      "1/2-1/2" => GameResult::Draw,
+      _ => return,
+    };
+    if self.outcome.is_some() || (result == GameResult::Draw && self.claim_draw()) {
+      return;
+    }
+    let termination = match (result, termination) {
+      (_, Some(tag)) if tag.eq_ignore_ascii_case("time forfeit") => Termination::Timeout,
+      (GameResult::Draw, _) => Termination::Agreement,
+      _ => Termination::Resignation,
+    };
+    self.state = GameState::GameOver;
+    self.outcome = Some((result, termination));
+  }
+
+  fn parse_tag_pair(line: &str) -> Result<(String, String), PgnError> {
+    let invalid = || PgnError::InvalidTag(String::from(line));
+    let inner = line
+      .strip_prefix('[')
+      .and_then(|rest| rest.strip_suffix(']'))
+      .ok_or_else(invalid)?;
+    let (name, rest) = inner
+      .trim()
+      .split_at(inner.trim().find('"').ok_or_else(invalid)?);
+    let name = name.trim();
+    if name.is_empty() || !rest.ends_with('"') || rest.len() < 2 {
+      return Err(invalid());
+    }
+
+    // backslashes escape quotes and themselves inside the value
+    let mut value = String::new();
+    let mut chars = rest[1..rest.len() - 1].chars();
+    while let Some(c) = chars.next() {
+      match c {
+        '\\' => value.push(chars.next().ok_or_else(invalid)?),
+        '"' => return Err(invalid()),
+        _ => value.push(c),
+      }
+    }
+    Ok((String::from(name), value))
+  }
+
+  pub fn set_pgn_tag(&mut self, name: &str, value: &str) {
+    match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+      Some(tag) => tag.1 = String::from(value),
+      None => self.tags.push((String::from(name), String::from(value))),
+    }
+  }
+
+  pub fn to_pgn(&self) -> String {
+    let result = match self.get_result() {
+      Some(result) => result.to_string(),
+      None => String::from("*"),
+    };
+    let mut pgn = String::new();
+    for (name, default) in SEVEN_TAG_ROSTER.iter() {
+      let value = match *name {
+        "Result" => &result,
+        _ => self.get_tag(name).unwrap_or(default),
+      };
+      pgn.push_str(&Game::tag_pair(name, value));
+    }
+    // a game that didn't start from the standard position needs it to be replayed
+    if self.starting_fen != STARTING_FEN {
+      pgn.push_str(&Game::tag_pair("SetUp", "1"));
+      pgn.push_str(&Game::tag_pair("FEN", &self.starting_fen));
+    }
+    for (name, value) in self.tags.iter() {
+      let reserved = SEVEN_TAG_ROSTER.iter().any(|(tag, _)| tag == name);
+      if !reserved && name != "SetUp" && name != "FEN" {
+        pgn.push_str(&Game::tag_pair(name, value));
+      }
+    }
+    pgn.push('\n');
+
+    // the fields of the starting position tell the number and side of the first move
+    let fields: Vec<&str> = self.starting_fen.split_whitespace().collect();
+    let mut turn = match fields[1] {
+      "w" => Colour::White,
+      _ => Colour::Black,
+    };
+    let mut fullmove_number: u32 = fields[5].parse().unwrap();
+
+    let mut tokens = vec![];
+    for (i, san) in self.moves.iter().enumerate() {
+      if turn == Colour::White {
+        tokens.push(format!("{}.", fullmove_number));
+      } else if i == 0 {
+        tokens.push(format!("{}...", fullmove_number));
+      }
+      tokens.push(san.clone());
+      if turn == Colour::Black {
+        fullmove_number += 1;
+      }
+      turn = turn.opponent();
+    }
+    tokens.push(result);
+
+    // keep the movetext lines under 80 characters
+    let mut line = String::new();
+    for token in tokens {
+      if !line.is_empty() && line.len() + token.len() + 1 > 79 {
+        pgn.push_str(&line);
+        pgn.push('\n');
+        line.clear();
+      }
+      if !line.is_empty() {
+        line.push(' ');
+      }
+      line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+  }
+
+  fn get_tag(&self, name: &str) -> Option<&str> {
+    self
+      .tags
+      .iter()
+      .find(|(tag, _)| tag == name)
+      .map(|(_, value)| value.as_str())
+  }
+
+  fn tag_pair(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]\n", name, value)
+  }
+}
@@ -0,0 +1,114 @@
+use crate::{best_move_with, Game, SearchLimits, SearchResult};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// the milliseconds kept back from every move for the GUI to get it in time
+pub(crate) const MOVE_OVERHEAD: u64 = 50;
+// when the GUI doesn't say how many moves are left to the next time control
+pub(crate) const MOVES_TO_GO: u64 = 30;
+
+// what the thread reading stdin and the one searching both look at
+#[derive(Debug, Default)]
+pub(crate) struct Signals {
+  // the search has to end as soon as it can
+  pub(crate) stop: AtomicBool,
+  // a search was asked for and its move isn't sent yet
+  pub(crate) searching: AtomicBool,
+  // the move of the search that is running isn't wanted any more
+  pub(crate) abort: AtomicBool,
+}
+
+// a protocol for talking to chess GUIs, one command per line
+pub(crate) trait Protocol {
+  fn answer(&mut self, name: &str, args: &[&str], out: &mut dyn Write) -> io::Result<()>;
+
+  // answers one command, returns false when it is time to quit
+  fn handle(&mut self, command: &str, out: &mut dyn Write) -> bool {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.split_first() {
+      None => true,
+      Some((&"quit", _)) => false,
+      // the GUI is gone if its end of stdout is closed
+      Some((name, args)) => self.answer(name, args, out).is_ok(),
+    }
+  }
+}
+
+// speaks the protocol over stdin and stdout until quit, early is given every line
+// on the reading thread and says whether it is dealt with there instead of passed on
+pub(crate) fn run<P: Protocol>(new: fn(Arc<Signals>) -> P, early: fn(&str, &Signals) -> bool) {
+  let signals = Arc::new(Signals::default());
+  let commands = read_commands(Arc::clone(&signals), early);
+  let mut protocol = new(signals);
+  for command in commands {
+    // stdout is locked for each line, so the reading thread can write too
+    if !protocol.handle(&command, &mut io::stdout()) {
+      break;
+    }
+  }
+}
+
+// stdin is read on a thread of its own, so a search can be stopped while it runs
+fn read_commands(
+  signals: Arc<Signals>,
+  early: fn(&str, &Signals) -> bool,
+) -> mpsc::Receiver<String> {
+  let (sender, receiver) = mpsc::channel();
+  thread::spawn(move || {
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+      let line = match line {
+        Ok(line) => line,
+        Err(_) => break,
+      };
+      if early(&line, &signals) {
+        continue;
+      }
+      let quit = line.split_whitespace().next() == Some("quit");
+      if quit {
+        signals.stop.store(true, Ordering::Relaxed);
+      }
+      if sender.send(line).is_err() || quit {
+        return;
+      }
+    }
+    // the GUI is gone, which is the same as quit
+    signals.stop.store(true, Ordering::Relaxed);
+    let _ = sender.send(String::from("quit"));
+  });
+  receiver
+}
+
+// searches until a limit is reached or stop is set, and writes the line report
+// gives for every depth that is finished, if it gives one
+pub(crate) fn search(
+  game: &Game,
+  limits: SearchLimits,
+  stop: &AtomicBool,
+  out: &mut dyn Write,
+  report: &dyn Fn(&SearchResult, Duration) -> Option<String>,
+) -> io::Result<Option<SearchResult>> {
+  let start = Instant::now();
+  let mut written = Ok(());
+  let result = best_move_with(game, limits, stop, &mut |result| {
+    if let (Ok(()), Some(line)) = (&written, report(result, start.elapsed())) {
+      written = writeln!(out, "{}", line);
+    }
+  });
+  written.map(|_| result)
+}
+
+// the time for a move when it is always the same, all in milliseconds
+pub(crate) fn fixed_time(time: u64, overhead: u64) -> Duration {
+  Duration::from_millis(time.saturating_sub(overhead).max(1))
+}
+
+// a share of the time left and most of what comes back after the move,
+// but never more than is left, all in milliseconds
+pub(crate) fn time_share(time: u64, increment: u64, moves_to_go: u64, overhead: u64) -> Duration {
+  let share = time / moves_to_go.max(1) + increment * 3 / 4;
+  Duration::from_millis(share.min(time.saturating_sub(overhead)).max(1))
+}
@@ -0,0 +1,115 @@
+use crate::{Colour, Game, GameState};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameResult {
+  WhiteWins,
+  BlackWins,
+  Draw,
+}
+
+// why the game ended
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Termination {
+  Checkmate,
+  Resignation,
+  Timeout,
+  Stalemate,
+  // threefold when claimed, fivefold on its own
+  Repetition,
+  // fifty moves when claimed, seventy-five on its own
+  FiftyMoveRule,
+  InsufficientMaterial,
+  Agreement,
+}
+
+impl GameResult {
+  pub(crate) fn win_for(colour: Colour) -> GameResult {
+    match colour {
+      Colour::White => GameResult::WhiteWins,
+      Colour::Black => GameResult::BlackWins,
+    }
+  }
+}
+
+// the result as written in PGN
+impl fmt::Display for GameResult {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let result = match self {
+      GameResult::WhiteWins => "1-0",
+      GameResult::BlackWins => "0-1",
+      GameResult::Draw => "1/2-1/2",
+    };
+    write!(f, "{}", result)
+  }
+}
+
+impl Game {
+  pub fn get_result(&self) -> Option<GameResult> {
+    self.outcome.map(|(result, _)| result)
+  }
+
+  pub fn get_termination(&self) -> Option<Termination> {
+    self.outcome.map(|(_, termination)| termination)
+  }
+
+  // the endings that follow from the position, the others are set where they happen
+  pub(crate) fn update_outcome(&mut self) {
+    self.outcome = match self.state {
+      // the side to move is the one that got mated
+      GameState::Checkmate => Some((
+        GameResult::win_for(self.turn.opponent()),
+        Termination::Checkmate,
+      )),
+      GameState::Stalemate => Some((GameResult::Draw, Termination::Stalemate)),
+      GameState::SeventyFiveMoveRule => Some((GameResult::Draw, Termination::FiftyMoveRule)),
+      GameState::FivefoldRepetition => Some((GameResult::Draw, Termination::Repetition)),
+      GameState::InsufficientMaterial => {
+        Some((GameResult::Draw, Termination::InsufficientMaterial))
+      }
+      _ => None,
+    };
+  }
+
+  // ends the game as a win for the other side, returns false if it was already over
+  pub fn resign(&mut self, colour: Colour) -> bool {
+    if self.is_game_over() {
+      return false;
+    }
+    self.state = GameState::GameOver;
+    self.outcome = Some((
+      GameResult::win_for(colour.opponent()),
+      Termination::Resignation,
+    ));
+    true
+  }
+
+  // the offer stands until the other side accepts, declines or makes a move
+  pub fn offer_draw(&mut self, colour: Colour) -> bool {
+    if self.is_game_over() {
+      return false;
+    }
+    self.draw_offer = Some(colour);
+    true
+  }
+
+  // only the side to move can accept, and only an offer of the other side
+  pub fn accept_draw(&mut self) -> bool {
+    if self.draw_offer != Some(self.turn.opponent()) || self.is_game_over() {
+      return false;
+    }
+    self.draw_offer = None;
+    self.state = GameState::GameOver;
+    self.outcome = Some((GameResult::Draw, Termination::Agreement));
+    true
+  }
+
+  pub fn decline_draw(&mut self) -> bool {
+    self.draw_offer.take().is_some()
+  }
+
+  // the side that offered a draw, if the offer still stands
+  pub fn get_draw_offer(&self) -> Option<Colour> {
+    self.draw_offer
+  }
+}
@@ -0,0 +1,217 @@
+use crate::{Colour, Game, GameState, Move, Piece, Position, Square};
+
+impl Game {
+  // the move in standard algebraic notation, the check suffix is added after the move is made
+  pub(crate) fn san_without_suffix(
+    &self,
+    from: &Position,
+    to: &Position,
+    promotion: Option<Piece>,
+  ) -> String {
+    let piece = match self.board[from.0][from.1] {
+      Some(piece) => piece,
+      None => return String::new(),
+    };
+    let destination = Game::parse_coordinates(to.clone());
+    let capture = self.board[to.0][to.1].is_some();
+
+    let mut san = String::new();
+    match piece {
+      Piece::King(_) if from.1 == 4 && to.1 == 6 => return String::from("O-O"),
+      Piece::King(_) if from.1 == 4 && to.1 == 2 => return String::from("O-O-O"),
+      Piece::Pawn(_) => {
+        // pawns only show where they came from when capturing, en passant included
+        if from.1 != to.1 {
+          san.push(Game::parse_coordinates(from.clone()).remove(0));
+          san.push('x');
+        }
+        san.push_str(&destination);
+        if let Some(new_piece) = promotion {
+          san.push('=');
+          san.push(new_piece.to_fen().to_ascii_uppercase());
+        }
+        return san;
+      }
+      _ => san.push(piece.to_fen().to_ascii_uppercase()),
+    }
+
+    // another piece of the same kind that can reach the same tile has to be told apart
+    let mut same_file = false;
+    let mut same_rank = false;
+    let mut ambiguous = false;
+    for i in 0..=7 {
+      for j in 0..=7 {
+        if (i, j) == (from.0, from.1) || self.board[i][j] != Some(piece) {
+          continue;
+        }
+        let moves = self
+          .get_possible_moves(Game::parse_coordinates(Position(i, j)))
+          .unwrap();
+        if moves.contains(&destination) {
+          ambiguous = true;
+          same_file |= j == from.1;
+          same_rank |= i == from.0;
+        }
+      }
+    }
+    if ambiguous {
+      let from_tile = Game::parse_coordinates(from.clone());
+      if !same_file {
+        san.push_str(&from_tile[..1]);
+      } else if !same_rank {
+        san.push_str(&from_tile[1..]);
+      } else {
+        san.push_str(&from_tile);
+      }
+    }
+
+    if capture {
+      san.push('x');
+    }
+    san.push_str(&destination);
+    san
+  }
+
+  // '#' for checkmate and '+' for check, given the position after the move
+  pub(crate) fn check_suffix(&self) -> &'static str {
+    let side = match self.turn {
+      Colour::White => "white",
+      Colour::Black => "black",
+    };
+    if self.state == GameState::Checkmate {
+      "#"
+    } else if self.check(String::from(side)) {
+      "+"
+    } else {
+      ""
+    }
+  }
+}
+
+impl Game {
+  // the piece that a SAN or promotion letter stands for, for the side to move
+  fn san_piece(&self, letter: char) -> Option<Piece> {
+    if !"KQRBNP".contains(letter) {
+      return None;
+    }
+    match self.turn {
+      Colour::White => Piece::from_fen(letter),
+      Colour::Black => Piece::from_fen(letter.to_ascii_lowercase()),
+    }
+  }
+
+  // finds the legal move the SAN stands for, check marks and annotations may be left out
+  pub(crate) fn parse_san(&self, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(&['+', '#', '!', '?'][..]);
+    let row = match self.turn {
+      Colour::White => 0,
+      Colour::Black => 7,
+    };
+    let castling_col = match san {
+      "O-O" | "0-0" => Some(6),
+      "O-O-O" | "0-0-0" => Some(2),
+      _ => None,
+    };
+    if let Some(col) = castling_col {
+      if self.board[row][4] != Some(Piece::King(self.turn)) {
+        return None;
+      }
+      let moves = self.get_possible_moves(Game::parse_coordinates(Position(row, 4)))?;
+      let to = Position(row, col);
+      return match moves.contains(&Game::parse_coordinates(to.clone())) {
+        true => Some(Move::new(
+          Square::from_position(&Position(row, 4)),
+          Square::from_position(&to),
+          None,
+        )),
+        false => None,
+      };
+    }
+
+    let mut chars: Vec<char> = san.chars().collect();
+    // the '=' before the promoted piece is often left out
+    let mut promotion = None;
+    if chars.len() > 2 && chars[chars.len() - 1].is_ascii_uppercase() {
+      promotion = Some(self.san_piece(chars.pop().unwrap())?.get_kind());
+      if chars.last() == Some(&'=') {
+        chars.pop();
+      }
+    }
+    let piece = match chars.first() {
+      Some(letter) if letter.is_ascii_uppercase() => self.san_piece(chars.remove(0))?,
+      _ => Piece::Pawn(self.turn),
+    };
+    if chars.len() < 2 {
+      return None;
+    }
+    let destination: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let to = Game::parse_tile(&destination)?;
+    if chars.last() == Some(&'x') {
+      chars.pop();
+    }
+
+    // what is left tells the file, rank or tile the piece came from
+    let mut from_col = None;
+    let mut from_row = None;
+    for c in chars {
+      match c {
+        'a'..='h' if from_col.is_none() && from_row.is_none() => {
+          from_col = Some(c as usize - 'a' as usize)
+        }
+        '1'..='8' if from_row.is_none() => from_row = Some(c as usize - '1' as usize),
+        _ => return None,
+      }
+    }
+
+    // pawns only leave their file when capturing, and then the file is always given
+    if let Piece::Pawn(_) = piece {
+      from_col = from_col.or(Some(to.1));
+    }
+
+    let mut found = None;
+    for i in 0..=7 {
+      for j in 0..=7 {
+        if self.board[i][j] != Some(piece)
+          || from_row.unwrap_or(i) != i
+          || from_col.unwrap_or(j) != j
+        {
+          continue;
+        }
+        let moves = self.get_possible_moves(Game::parse_coordinates(Position(i, j)))?;
+        if moves.contains(&destination) {
+          // a SAN that fits more than one move doesn't tell which one was meant
+          if found.is_some() {
+            return None;
+          }
+          found = Some(Position(i, j));
+        }
+      }
+    }
+    // whether the promotion fits the move is left to apply_move
+    found.map(|from| {
+      Move::new(
+        Square::from_position(&from),
+        Square::from_position(&to),
+        promotion,
+      )
+    })
+  }
+
+  pub fn make_san_move(&mut self, san: &str) -> Option<GameState> {
+    let mv = self.parse_san(san)?;
+    self.apply_move(&mv)
+  }
+
+  // the move in SAN with its check or checkmate suffix, if it is legal, promoting to a queen
+  pub fn to_san(&self, from: String, to: String) -> Option<String> {
+    let mut game = self.clone();
+    game.make_move(from, to)?;
+    game.moves.pop()
+  }
+
+  pub fn move_to_san(&self, mv: &Move) -> Option<String> {
+    let mut game = self.clone();
+    game.apply_move(mv)?;
+    game.moves.pop()
+  }
+}
@@ -0,0 +1,476 @@
+use crate::{evaluate, Game, Move, PieceKind};
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// a score this high or higher is a mate, the fewer plies to it the higher
+const MATE: i32 = 30_000;
+const INFINITY: i32 = 32_000;
+const MAX_PLY: usize = 64;
+// the number of positions the transposition table remembers, a power of two
+const TABLE_SIZE: usize = 1 << 18;
+
+// when to stop searching, the search stops at whichever limit it reaches first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchLimits {
+  pub depth: Option<u32>,
+  pub time: Option<Duration>,
+  pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+  pub fn depth(depth: u32) -> SearchLimits {
+    SearchLimits {
+      depth: Some(depth),
+      ..SearchLimits::default()
+    }
+  }
+
+  pub fn time(time: Duration) -> SearchLimits {
+    SearchLimits {
+      time: Some(time),
+      ..SearchLimits::default()
+    }
+  }
+
+  pub fn nodes(nodes: u64) -> SearchLimits {
+    SearchLimits {
+      nodes: Some(nodes),
+      ..SearchLimits::default()
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+  pub best_move: Move,
+  // in centipawns for the side to move, see SearchResult::get_mate_in for mates
+  pub score: i32,
+  // the moves both sides are expected to make, starting with the best move
+  pub pv: Vec<Move>,
+  // the deepest search that was finished, in plies
+  pub depth: u32,
+  pub nodes: u64,
+}
+
+impl SearchResult {
+  // the moves until mate, negative when it is the side to move that gets mated
+  pub fn get_mate_in(&self) -> Option<i32> {
+    if self.score.abs() < MATE - MAX_PLY as i32 {
+      return None;
+    }
+    let moves = (MATE - self.score.abs() + 1) / 2;
+    match self.score > 0 {
+      true => Some(moves),
+      false => Some(-moves),
+    }
+  }
+}
+
+// searches deeper and deeper until a limit is reached, returns None if there is no move to make
+pub fn best_move(game: &Game, limits: SearchLimits) -> Option<SearchResult> {
+  best_move_with(game, limits, &AtomicBool::new(false), &mut |_| {})
+}
+
+// like best_move, but it also stops as soon as stop is set from another thread,
+// and report is called with the result of every depth once it is finished
+pub fn best_move_with(
+  game: &Game,
+  limits: SearchLimits,
+  stop: &AtomicBool,
+  report: &mut dyn FnMut(&SearchResult),
+) -> Option<SearchResult> {
+  if game.is_game_over() || game.get_all_legal_moves().is_empty() {
+    return None;
+  }
+  Search::new(game, limits, stop).run(report)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Bound {
+  Exact,
+  // the score is at least this, the search stopped at a move that was too good
+  Lower,
+  // the score is at most this, no move was good enough
+  Upper,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Entry {
+  key: u64,
+  depth: i32,
+  score: i32,
+  bound: Bound,
+  best_move: Option<Move>,
+}
+
+// what was found for positions searched before, by their Zobrist key
+struct TranspositionTable {
+  entries: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+  fn new() -> TranspositionTable {
+    TranspositionTable {
+      entries: vec![None; TABLE_SIZE],
+    }
+  }
+
+  fn probe(&self, key: u64) -> Option<Entry> {
+    match self.entries[key as usize & (TABLE_SIZE - 1)] {
+      Some(entry) if entry.key == key => Some(entry),
+      _ => None,
+    }
+  }
+
+  // the newest entry always takes the place of the old one
+  fn store(&mut self, entry: Entry) {
+    self.entries[entry.key as usize & (TABLE_SIZE - 1)] = Some(entry);
+  }
+}
+
+struct Search<'a> {
+  game: Game,
+  limits: SearchLimits,
+  stop: &'a AtomicBool,
+  start: Instant,
+  nodes: u64,
+  stopped: bool,
+  table: TranspositionTable,
+  // two quiet moves for each ply that were good enough to stop the search before
+  killers: [[Option<Move>; 2]; MAX_PLY],
+  // how often quiet moves between two tiles stopped the search, weighted by depth
+  history: [[i32; 64]; 64],
+  // the keys of the positions since the last capture or pawn move, the current one last
+  keys: Vec<u64>,
+}
+
+impl<'a> Search<'a> {
+  fn new(game: &Game, limits: SearchLimits, stop: &'a AtomicBool) -> Search<'a> {
+    // the positions before the search started count for repetitions too
+    let mut previous = game.clone();
+    let mut keys = vec![game.hash()];
+    for _ in 0..game.halfmove_clock {
+      if previous.undo_stack.is_empty() {
+        break;
+      }
+      previous.undo_quietly();
+      keys.push(previous.hash());
+    }
+    keys.reverse();
+
+    Search {
+      game: game.clone(),
+      limits,
+      stop,
+      start: Instant::now(),
+      nodes: 0,
+      stopped: false,
+      table: TranspositionTable::new(),
+      killers: [[None; 2]; MAX_PLY],
+      history: [[0; 64]; 64],
+      keys,
+    }
+  }
+
+  fn run(&mut self, report: &mut dyn FnMut(&SearchResult)) -> Option<SearchResult> {
+    // there is always a move to give back, so at least one ply is searched
+    let max_depth = self
+      .limits
+      .depth
+      .unwrap_or(MAX_PLY as u32)
+      .clamp(1, MAX_PLY as u32 / 2);
+    let mut result: Option<SearchResult> = None;
+    for depth in 1..=max_depth {
+      let mut pv = vec![];
+      let score = self.negamax(depth as i32, 0, -INFINITY, INFINITY, &mut pv);
+      // an unfinished search is thrown away, unless there is nothing else
+      if self.stopped && result.is_some() {
+        break;
+      }
+      let best_move = match pv.first() {
+        Some(mv) => *mv,
+        None => self.game.get_all_legal_moves()[0],
+      };
+      let finished = SearchResult {
+        best_move,
+        score,
+        pv,
+        depth,
+        nodes: self.nodes,
+      };
+      if !self.stopped {
+        report(&finished);
+      }
+      result = Some(finished);
+      // a shorter mate can't be found by searching deeper
+      if self.stopped || score.abs() >= MATE - depth as i32 {
+        break;
+      }
+      // the next depth takes longer than all the ones before it together
+      if let Some(time) = self.limits.time {
+        if self.start.elapsed() * 2 >= time {
+          break;
+        }
+      }
+    }
+    result.map(|result| SearchResult {
+      nodes: self.nodes,
+      ..result
+    })
+  }
+
+  fn negamax(
+    &mut self,
+    depth: i32,
+    ply: usize,
+    mut alpha: i32,
+    beta: i32,
+    pv: &mut Vec<Move>,
+  ) -> i32 {
+    pv.clear();
+    self.nodes += 1;
+    if self.should_stop() {
+      return 0;
+    }
+    if ply > 0 && self.is_draw() {
+      return 0;
+    }
+    // a check is never the end of the line, there is always a reply to look at
+    let in_check = self.game.is_in_check();
+    let depth = if in_check { depth + 1 } else { depth };
+    if depth <= 0 || ply >= MAX_PLY - 1 {
+      return self.quiescence(ply, alpha, beta);
+    }
+
+    let key = self.game.hash();
+    let entry = self.table.probe(key);
+    if let Some(entry) = entry {
+      if ply > 0 && entry.depth >= depth {
+        let score = from_table(entry.score, ply);
+        match entry.bound {
+          Bound::Exact => return score,
+          Bound::Lower if score >= beta => return score,
+          Bound::Upper if score <= alpha => return score,
+          _ => {}
+        }
+      }
+    }
+
+    let mut moves = self.game.get_all_legal_moves();
+    if moves.is_empty() {
+      return match in_check {
+        true => -(MATE - ply as i32),
+        false => 0,
+      };
+    }
+    let table_move = entry.and_then(|entry| entry.best_move);
+    moves.sort_by_key(|mv| Reverse(self.order(mv, table_move, ply)));
+
+    let original_alpha = alpha;
+    let mut best_score = -INFINITY;
+    let mut best_move = None;
+    let mut line = vec![];
+    for mv in moves {
+      self.make(&mv);
+      let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+      self.unmake();
+      if self.stopped {
+        return 0;
+      }
+      if score <= best_score {
+        continue;
+      }
+      best_score = score;
+      best_move = Some(mv);
+      if score > alpha {
+        alpha = score;
+        pv.clear();
+        pv.push(mv);
+        pv.extend_from_slice(&line);
+      }
+      if alpha >= beta {
+        if !mv.flags.capture && mv.promotion.is_none() {
+          self.remember_quiet_move(&mv, depth, ply);
+        }
+        break;
+      }
+    }
+
+    let bound = if best_score >= beta {
+      Bound::Lower
+    } else if best_score > original_alpha {
+      Bound::Exact
+    } else {
+      Bound::Upper
+    };
+    self.table.store(Entry {
+      key,
+      depth,
+      score: to_table(best_score, ply),
+      bound,
+      best_move,
+    });
+    best_score
+  }
+
+  // only captures and promotions, so the search never stops in the middle of an exchange
+  fn quiescence(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+    self.nodes += 1;
+    if self.should_stop() {
+      return 0;
+    }
+    // the side to move doesn't have to capture, so it gets at least the score as it is
+    let stand_pat = evaluate(&self.game);
+    if stand_pat >= beta || ply >= MAX_PLY - 1 {
+      return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+
+    let mut moves: Vec<Move> = self
+      .game
+      .get_all_legal_moves()
+      .into_iter()
+      .filter(|mv| mv.flags.capture || mv.promotion.is_some())
+      .collect();
+    moves.sort_by_key(|mv| Reverse(self.order(mv, None, ply)));
+    for mv in moves {
+      self.make(&mv);
+      let score = -self.quiescence(ply + 1, -beta, -alpha);
+      self.unmake();
+      if self.stopped {
+        return 0;
+      }
+      if score >= beta {
+        return score;
+      }
+      alpha = alpha.max(score);
+    }
+    alpha
+  }
+
+  // the move from the table first, then captures of the most valuable piece
+  // by the least valuable one, then the killers and the rest by their history
+  fn order(&self, mv: &Move, table_move: Option<Move>, ply: usize) -> i32 {
+    if Some(*mv) == table_move {
+      return 1_000_000;
+    }
+    let from = mv.from.to_position();
+    let to = mv.to.to_position();
+    if mv.flags.capture {
+      // en passant is the only capture to an empty tile
+      let victim = self.game.board[to.0][to.1].map_or(1, |piece| rank(piece.get_kind()));
+      let attacker = self.game.board[from.0][from.1].map_or(1, |piece| rank(piece.get_kind()));
+      return 100_000 + 10 * victim - attacker;
+    }
+    if mv.promotion == Some(PieceKind::Queen) {
+      return 90_000;
+    }
+    if self.killers[ply][0] == Some(*mv) {
+      return 80_000;
+    }
+    if self.killers[ply][1] == Some(*mv) {
+      return 79_000;
+    }
+    self.history[from.0 * 8 + from.1][to.0 * 8 + to.1]
+  }
+
+  fn remember_quiet_move(&mut self, mv: &Move, depth: i32, ply: usize) {
+    if self.killers[ply][0] != Some(*mv) {
+      self.killers[ply][1] = self.killers[ply][0];
+      self.killers[ply][0] = Some(*mv);
+    }
+    let from = mv.from.to_position();
+    let to = mv.to.to_position();
+    self.history[from.0 * 8 + from.1][to.0 * 8 + to.1] += depth * depth;
+  }
+
+  fn make(&mut self, mv: &Move) {
+    self.game.make_move_quietly(mv);
+    self.keys.push(self.game.hash());
+  }
+
+  fn unmake(&mut self) {
+    self.game.undo_quietly();
+    self.keys.pop();
+  }
+
+  // any repetition counts as a draw, playing for a third one is never better
+  fn is_draw(&self) -> bool {
+    if self.game.halfmove_clock >= 100 || self.game.insufficient_material() {
+      return true;
+    }
+    let key = self.game.hash();
+    self
+      .keys
+      .iter()
+      .rev()
+      .take(self.game.halfmove_clock as usize + 1)
+      .skip(1)
+      .any(|previous| *previous == key)
+  }
+
+  fn should_stop(&mut self) -> bool {
+    if let Some(nodes) = self.limits.nodes {
+      if self.nodes >= nodes {
+        self.stopped = true;
+      }
+    }
+    // looking at the time and the flag is slow compared to searching a node
+    if self.nodes & 1023 == 0 {
+      if self.stop.load(Ordering::Relaxed) {
+        self.stopped = true;
+      }
+      if let Some(time) = self.limits.time {
+        if self.start.elapsed() >= time {
+          self.stopped = true;
+        }
+      }
+    }
+    self.stopped
+  }
+}
+
+// mates are stored as plies from the position instead of from the root of the search
+fn to_table(score: i32, ply: usize) -> i32 {
+  if score >= MATE - MAX_PLY as i32 {
+    score + ply as i32
+  } else if score <= -(MATE - MAX_PLY as i32) {
+    score - ply as i32
+  } else {
+    score
+  }
+}
+
+fn from_table(score: i32, ply: usize) -> i32 {
+  if score >= MATE - MAX_PLY as i32 {
+    score - ply as i32
+  } else if score <= -(MATE - MAX_PLY as i32) {
+    score + ply as i32
+  } else {
+    score
+  }
+}
+
+// the pieces from least to most valuable, for ordering captures
+fn rank(kind: PieceKind) -> i32 {
+  match kind {
+    PieceKind::Pawn => 1,
+    PieceKind::Knight => 2,
+    PieceKind::Bishop => 3,
+    PieceKind::Rook => 4,
+    PieceKind::Queen => 5,
+    PieceKind::King => 6,
+  }
+}
+
+impl Game {
+  fn is_in_check(&self) -> bool {
+    let king = self.bitboards.get_king(self.turn);
+    let occupied = self.bitboards.occupied();
+    self
+      .bitboards
+      .attackers(king, self.turn.opponent(), occupied)
+      != 0
+  }
+}
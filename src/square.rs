@@ -0,0 +1,60 @@
+use crate::{Game, Position};
+use std::fmt;
+
+// a tile on the board, files and ranks are counted from 0 so a1 is (0, 0) and h8 is (7, 7)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square {
+  file: u8,
+  rank: u8,
+}
+
+impl Square {
+  pub fn new(file: u8, rank: u8) -> Option<Square> {
+    if file < 8 && rank < 8 {
+      Some(Square { file, rank })
+    } else {
+      None
+    }
+  }
+
+  // a tile written like "e4"
+  pub fn from_name(name: &str) -> Option<Square> {
+    Game::parse_tile(name).map(|position| Square::from_position(&position))
+  }
+
+  // every tile, from a1 to h8 rank by rank
+  pub fn all() -> Vec<Square> {
+    let mut squares = vec![];
+    for rank in 0..8 {
+      for file in 0..8 {
+        squares.push(Square { file, rank });
+      }
+    }
+    squares
+  }
+
+  pub fn get_file(&self) -> u8 {
+    self.file
+  }
+
+  pub fn get_rank(&self) -> u8 {
+    self.rank
+  }
+
+  pub(crate) fn from_position(position: &Position) -> Square {
+    Square {
+      file: position.1 as u8,
+      rank: position.0 as u8,
+    }
+  }
+
+  pub(crate) fn to_position(self) -> Position {
+    Position(self.rank as usize, self.file as usize)
+  }
+}
+
+impl fmt::Display for Square {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", Game::parse_coordinates(self.to_position()))
+  }
+}
@@ -1,5 +1,24 @@
+#![allow(clippy::bool_assert_comparison, clippy::module_inception)]
+
 #[cfg(test)]
 mod tests {
+  use crate::protocol::{Protocol, Signals};
+  use std::sync::Arc;
+
+  // the lines a protocol writes back for the commands
+  fn run<P: Protocol>(new: fn(Arc<Signals>) -> P, commands: &[&str]) -> Vec<String> {
+    let mut engine = new(Arc::new(Signals::default()));
+    let mut out = vec![];
+    for command in commands {
+      engine.handle(command, &mut out);
+    }
+    String::from_utf8(out)
+      .unwrap()
+      .lines()
+      .map(String::from)
+      .collect()
+  }
+
   mod init {
     use crate::Colour;
     use crate::Game;
@@ -7,137 +26,2049 @@ mod tests {
     use crate::Piece;
 
     #[test]
-    fn initializes_correctly() {
-      //let game = Game::new();
-      let game = Game::new_from_fen(String::from(
-        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
-      ));
-      assert_eq!(game.turn, Colour::White);
-      assert_eq!(game.board[0][0].unwrap(), Piece::Rook(Colour::White));
-      assert_eq!(game.board[0][1].unwrap(), Piece::Knight(Colour::White));
-      assert_eq!(game.board[0][2].unwrap(), Piece::Bishop(Colour::White));
-      assert_eq!(game.board[0][3].unwrap(), Piece::Queen(Colour::White));
-      assert_eq!(game.board[0][4].unwrap(), Piece::King(Colour::White));
-      assert_eq!(game.board[0][5].unwrap(), Piece::Bishop(Colour::White));
-      assert_eq!(game.board[0][6].unwrap(), Piece::Knight(Colour::White));
-      assert_eq!(game.board[0][7].unwrap(), Piece::Rook(Colour::White));
+    fn initializes_correctly() {
+      //let game = Game::new();
+      let game = Game::new_from_fen(String::from(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+      ));
+      assert_eq!(game.turn, Colour::White);
+      assert_eq!(game.board[0][0].unwrap(), Piece::Rook(Colour::White));
+      assert_eq!(game.board[0][1].unwrap(), Piece::Knight(Colour::White));
+      assert_eq!(game.board[0][2].unwrap(), Piece::Bishop(Colour::White));
+      assert_eq!(game.board[0][3].unwrap(), Piece::Queen(Colour::White));
+      assert_eq!(game.board[0][4].unwrap(), Piece::King(Colour::White));
+      assert_eq!(game.board[0][5].unwrap(), Piece::Bishop(Colour::White));
+      assert_eq!(game.board[0][6].unwrap(), Piece::Knight(Colour::White));
+      assert_eq!(game.board[0][7].unwrap(), Piece::Rook(Colour::White));
+
+      for i in 0..=7 {
+        assert_eq!(game.board[1][i].unwrap(), Piece::Pawn(Colour::White));
+      }
+
+      for i in 0..=7 {
+        assert_eq!(game.board[2][i].is_none(), true);
+      }
+
+      for i in 0..=7 {
+        assert_eq!(game.board[6][i].unwrap(), Piece::Pawn(Colour::Black));
+      }
+
+      assert_eq!(game.board[7][0].unwrap(), Piece::Rook(Colour::Black));
+      assert_eq!(game.board[7][1].unwrap(), Piece::Knight(Colour::Black));
+      assert_eq!(game.board[7][2].unwrap(), Piece::Bishop(Colour::Black));
+      assert_eq!(game.board[7][3].unwrap(), Piece::Queen(Colour::Black));
+      assert_eq!(game.board[7][4].unwrap(), Piece::King(Colour::Black));
+      assert_eq!(game.board[7][5].unwrap(), Piece::Bishop(Colour::Black));
+      assert_eq!(game.board[7][6].unwrap(), Piece::Knight(Colour::Black));
+      assert_eq!(game.board[7][7].unwrap(), Piece::Rook(Colour::Black));
+
+      let game = Game::new_from_fen(String::from("4k2r/6r1/8/8/8/8/3R4/R3K3 b Qk - 0 1"));
+      assert_eq!(game.turn, Colour::Black);
+    }
+
+    #[test]
+    fn random_fen_inits() {
+      let game = Game::new_from_fen(String::from("4k2r/6r1/8/8/8/8/3R4/R3K3 w Qk - 0 1"));
+      assert_eq!(game.turn, Colour::White);
+
+      assert_eq!(game.board[0][0].unwrap(), Piece::Rook(Colour::White));
+      assert_eq!(game.board[1][3].unwrap(), Piece::Rook(Colour::White));
+      assert_eq!(game.board[6][6].unwrap(), Piece::Rook(Colour::Black));
+      assert_eq!(game.board[7][7].unwrap(), Piece::Rook(Colour::Black));
+      assert_eq!(game.board[7][6].is_none(), true);
+      assert_eq!(game.board[0][4].unwrap(), Piece::King(Colour::White));
+      assert_eq!(game.board[7][4].unwrap(), Piece::King(Colour::Black));
+      assert_eq!(game.board[0][5].is_none(), true);
+
+      let game = Game::new_from_fen(String::from(
+        "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50",
+      ));
+      assert_eq!(game.turn, Colour::Black);
+
+      assert_eq!(game.board[2][0].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[4][1].unwrap(), Piece::Pawn(Colour::Black));
+      assert_eq!(game.board[3][1].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[3][0].unwrap(), Piece::Pawn(Colour::Black));
+      assert_eq!(game.board[5][3].unwrap(), Piece::Pawn(Colour::Black));
+      assert_eq!(game.board[4][4].unwrap(), Piece::Pawn(Colour::Black));
+      assert_eq!(game.board[3][5].unwrap(), Piece::Pawn(Colour::Black));
+      assert_eq!(game.board[4][3].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[3][4].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[2][5].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[3][7].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[4][7].unwrap(), Piece::Pawn(Colour::Black));
+
+      assert_eq!(game.board[2][7].unwrap(), Piece::King(Colour::White));
+      assert_eq!(game.board[6][5].unwrap(), Piece::King(Colour::Black));
+
+      assert_eq!(game.board[0][0].is_none(), true);
+      assert_eq!(game.board[7][7].is_none(), true);
+      assert_eq!(game.board[5][7].is_none(), true);
+    }
+
+    #[test]
+    fn kings_are_in_place() {
+      let game = Game::new();
+      assert_eq!(
+        game.board[7][4].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::King(Colour::Black)
+      );
+      assert_eq!(
+        game.board[0][4].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::King(Colour::White)
+      );
+    }
+    #[test]
+    fn queens_are_in_place() {
+      let game = Game::new();
+      assert_eq!(
+        game.board[7][3].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::Queen(Colour::Black)
+      );
+      assert_eq!(
+        game.board[0][3].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::Queen(Colour::White)
+      );
+    }
+
+    #[test]
+    fn rooks_are_in_place() {
+      let game = Game::new();
+      assert_eq!(
+        game.board[0][0].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::Rook(Colour::White)
+      );
+      assert_eq!(
+        game.board[7][7].unwrap_or(Piece::Pawn(Colour::Black)),
+        Piece::Rook(Colour::Black)
+      );
+    }
+
+    #[test]
+    fn nones_in_place() {
+      let game = Game::new();
+      assert_eq!(game.board[3][5].is_none(), true);
+      assert_eq!(game.board[4][2].is_none(), true);
+    }
+
+    #[test]
+    fn gets_game_state() {
+      let mut game = Game::new();
+      assert_eq!(*game.get_game_state(), GameState::InProgress);
+      game.state = GameState::GameOver;
+      assert_eq!(*game.get_game_state(), GameState::GameOver);
+    }
+  }
+
+  mod fen {
+    use crate::Colour;
+    use crate::FenError;
+    use crate::FenField;
+    use crate::Game;
+    use crate::PositionError;
+
+    #[test]
+    fn exports_fen() {
+      let mut game = Game::new();
+      assert_eq!(
+        game.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+      );
+      game.make_move(String::from("e2"), String::from("e4"));
+      assert_eq!(
+        game.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+      );
+      game.make_move(String::from("g8"), String::from("f6"));
+      assert_eq!(
+        game.to_fen(),
+        "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+      );
+      game.make_move(String::from("e1"), String::from("e2"));
+      assert_eq!(
+        game.to_fen(),
+        "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+      );
+    }
+
+    #[test]
+    fn fen_round_trip() {
+      let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "4k2r/6r1/8/8/8/8/3R4/R3K3 w Qk - 0 1",
+        "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50",
+        "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+      ];
+      for fen in fens.iter() {
+        assert_eq!(Game::new_from_fen(String::from(*fen)).to_fen(), *fen);
+      }
+    }
+
+    #[test]
+    fn clocks_can_be_left_out() {
+      let game = Game::try_from_fen("4k2r/6r1/8/8/8/8/3R4/R3K3 w Qk -").unwrap();
+      assert_eq!(game.to_fen(), "4k2r/6r1/8/8/8/8/3R4/R3K3 w Qk - 0 1");
+    }
+
+    #[test]
+    fn rejects_malformed_fen() {
+      let err = |fen: &str| Game::try_from_fen(fen).unwrap_err();
+      assert_eq!(err(""), FenError::MissingField(FenField::Placement));
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"),
+        FenError::MissingField(FenField::Castling)
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"),
+        FenError::MissingField(FenField::FullmoveNumber)
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 1"),
+        FenError::TooManyFields
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        FenError::RankCount(7)
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        FenError::FileCount { rank: 2, files: 7 }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/5/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        FenError::FileCount { rank: 4, files: 5 }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/3X4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        FenError::InvalidCharacter {
+          field: FenField::Placement,
+          character: 'X'
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/9/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        FenError::InvalidCharacter {
+          field: FenField::Placement,
+          character: '9'
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+        FenError::InvalidValue {
+          field: FenField::SideToMove,
+          value: String::from("x")
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1"),
+        FenError::InvalidCharacter {
+          field: FenField::Castling,
+          character: 'x'
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KK - 0 1"),
+        FenError::InvalidCharacter {
+          field: FenField::Castling,
+          character: 'K'
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1"),
+        FenError::InvalidValue {
+          field: FenField::EnPassant,
+          value: String::from("e9")
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1"),
+        FenError::InvalidValue {
+          field: FenField::EnPassant,
+          value: String::from("e3")
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1"),
+        FenError::InvalidValue {
+          field: FenField::HalfmoveClock,
+          value: String::from("-1")
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"),
+        FenError::InvalidValue {
+          field: FenField::FullmoveNumber,
+          value: String::from("0")
+        }
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").to_string(),
+        "expected 8 ranks but found 7"
+      );
+    }
+
+    #[test]
+    fn rejects_illegal_positions() {
+      let err = |fen: &str| match Game::try_from_fen(fen) {
+        Err(FenError::IllegalPosition(err)) => err,
+        _ => panic!("{} should be illegal", fen),
+      };
+      assert_eq!(
+        err("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+        PositionError::MissingKing(Colour::Black)
+      );
+      assert_eq!(
+        err("4k3/8/8/8/8/8/8/4K2K w - - 0 1"),
+        PositionError::TooManyKings(Colour::White)
+      );
+      assert_eq!(
+        err("4k2p/8/8/8/8/8/8/4K3 w - - 0 1"),
+        PositionError::PawnOnBackRank(String::from("h8"))
+      );
+      assert_eq!(
+        err("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+        PositionError::PawnOnBackRank(String::from("a1"))
+      );
+      // black is in check but it is white to move
+      assert_eq!(
+        err("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1").to_string(),
+        String::from("the side not to move is in check")
+      );
+      assert_eq!(
+        err("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1"),
+        PositionError::OpponentInCheck
+      );
+      assert_eq!(
+        err("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1"),
+        PositionError::InvalidCastlingRights('K')
+      );
+      assert_eq!(
+        err("r3k2r/8/8/8/8/8/8/R2K3R w Q - 0 1"),
+        PositionError::InvalidCastlingRights('Q')
+      );
+      assert_eq!(
+        err("1r2k2r/8/8/8/8/8/8/R3K2R w q - 0 1"),
+        PositionError::InvalidCastlingRights('q')
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1"),
+        PositionError::InvalidEnPassant(String::from("e3"))
+      );
+      assert_eq!(
+        err("rnbqkbnr/pppppppp/8/8/4P3/4P3/PPPP2PP/RNBQKBNR b KQkq e3 0 1"),
+        PositionError::InvalidEnPassant(String::from("e3"))
+      );
+      assert_eq!(
+        Game::try_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").is_ok(),
+        true
+      );
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_from_fen_panics_on_malformed_fen() {
+      Game::new_from_fen(String::from(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+      ));
+    }
+  }
+
+  mod pgn {
+    use crate::{
+      Colour, FenError, Game, GameResult, GameState, PgnError, PositionError, Termination,
+    };
+
+    fn play(game: &mut Game, moves: &[(&str, &str)]) {
+      for (from, to) in moves.iter() {
+        assert_eq!(
+          game
+            .make_move(String::from(*from), String::from(*to))
+            .is_some(),
+          true
+        );
+      }
+    }
+
+    #[test]
+    fn exports_pgn() {
+      let mut game = Game::new();
+      game.set_pgn_tag("Event", "Scholar's \"mate\"");
+      game.set_pgn_tag("White", "alholmbe");
+      game.set_pgn_tag("Annotator", "nobody");
+      play(
+        &mut game,
+        &[
+          ("e2", "e4"),
+          ("e7", "e5"),
+          ("f1", "c4"),
+          ("b8", "c6"),
+          ("d1", "h5"),
+          ("g8", "f6"),
+          ("h5", "f7"),
+        ],
+      );
+      assert_eq!(
+        game.to_pgn(),
+        "[Event \"Scholar's \\\"mate\\\"\"]\n\
+         [Site \"?\"]\n\
+         [Date \"????.??.??\"]\n\
+         [Round \"?\"]\n\
+         [White \"alholmbe\"]\n\
+         [Black \"?\"]\n\
+         [Result \"1-0\"]\n\
+         [Annotator \"nobody\"]\n\
+         \n\
+         1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n"
+      );
+    }
+
+    #[test]
+    fn exports_pgn_from_fen() {
+      let mut game = Game::new_from_fen(String::from(
+        "r3k2r/ppp1qppp/8/3pP3/8/8/PPP1QPPP/R3K2R w KQkq d6 0 12",
+      ));
+      play(
+        &mut game,
+        &[("e5", "d6"), ("e8", "c8"), ("e1", "g1"), ("e7", "d6")],
+      );
+      let pgn = game.to_pgn();
+      assert_eq!(pgn.contains("[Result \"*\"]\n[SetUp \"1\"]\n"), true);
+      assert_eq!(
+        pgn.contains("[FEN \"r3k2r/ppp1qppp/8/3pP3/8/8/PPP1QPPP/R3K2R w KQkq d6 0 12\"]\n"),
+        true
+      );
+      assert_eq!(pgn.ends_with("\n12. exd6 O-O-O 13. O-O Qxd6 *\n"), true);
+
+      let mut game = Game::new_from_fen(String::from("4k3/8/8/8/8/3q4/8/4K3 b - - 0 40"));
+      play(&mut game, &[("d3", "e3")]);
+      assert_eq!(game.to_pgn().ends_with("\n40... Qe3+ *\n"), true);
+    }
+
+    #[test]
+    fn disambiguates_san() {
+      let mut game = Game::new_from_fen(String::from("k7/8/8/8/8/8/8/1N3N1K w - - 0 1"));
+      play(&mut game, &[("b1", "d2")]);
+      assert_eq!(game.moves, vec![String::from("Nbd2")]);
+
+      let mut game = Game::new_from_fen(String::from("7k/8/8/R7/8/8/8/R5K1 w - - 0 1"));
+      play(&mut game, &[("a1", "a3")]);
+      assert_eq!(game.moves, vec![String::from("R1a3")]);
+
+      let mut game = Game::new_from_fen(String::from("6k1/8/8/8/8/Q7/8/Q1Q4K w - - 0 1"));
+      play(&mut game, &[("a1", "b2")]);
+      assert_eq!(game.moves, vec![String::from("Qa1b2")]);
+
+      // a pinned knight can't move, so there is nothing to tell apart
+      let mut game = Game::new_from_fen(String::from("k7/8/8/8/8/8/8/1N2rN1K w - - 0 1"));
+      play(&mut game, &[("b1", "d2")]);
+      assert_eq!(game.moves, vec![String::from("Nd2")]);
+    }
+
+    #[test]
+    fn promotion_in_pgn() {
+      let mut game = Game::new_from_fen(String::from("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+      play(&mut game, &[("b7", "b8")]);
+      assert_eq!(game.moves, vec![String::from("b8=Q+")]);
+      assert_eq!(game.make_uci_move("e8d7"), Some(GameState::InProgress));
+      assert_eq!(game.make_uci_move("b8b7"), Some(GameState::Check));
+      assert_eq!(game.moves, vec!["b8=Q+", "Kd7", "Qb7+"]);
+    }
+
+    #[test]
+    fn imports_pgn() {
+      let game = Game::from_pgn(
+        "[Event \"Scholar's \\\"mate\\\"\"]\n\
+         [White \"alholmbe\"]\n\
+         \n\
+         % this line is skipped\n\
+         1. e4 {the king's pawn} e5 2. Bc4 $1 Nc6 (2... Nf6 3. d3) ; a comment\n\
+         3.Qh5 Nf6?? 4. Qxf7# 1-0\n",
+      )
+      .unwrap();
+      assert_eq!(game.get_game_state(), &GameState::Checkmate);
+      assert_eq!(
+        game.tags[0],
+        (String::from("Event"), String::from("Scholar's \"mate\""))
+      );
+      assert_eq!(
+        game.tags[1],
+        (String::from("White"), String::from("alholmbe"))
+      );
+      assert_eq!(game.moves.last(), Some(&String::from("Qxf7#")));
+      assert_eq!(
+        game.to_fen(),
+        "r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4"
+      );
+    }
+
+    #[test]
+    fn pgn_round_trip() {
+      let mut game = Game::new_from_fen(String::from("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1"));
+      play(&mut game, &[("e1", "g1"), ("e8", "d7")]);
+      assert_eq!(game.make_uci_move("b7b8n"), Some(GameState::Check));
+      game.set_pgn_tag("Annotator", "nobody");
+      let imported = Game::from_pgn(&game.to_pgn()).unwrap();
+      assert_eq!(imported.moves, vec!["O-O", "Kd7", "b8=N+"]);
+      assert_eq!(imported.to_fen(), game.to_fen());
+      assert_eq!(imported.to_pgn(), game.to_pgn());
+
+      // results that don't follow from the board are kept too
+      let pgn = "[Result \"1-0\"]\n\n1. e4 e5 2. Qh5 Nc6 1-0";
+      let imported = Game::from_pgn(pgn).unwrap();
+      assert_eq!(imported.get_result(), Some(GameResult::WhiteWins));
+      assert_eq!(imported.get_termination(), Some(Termination::Resignation));
+      assert_eq!(imported.to_pgn().contains("[Result \"1-0\"]"), true);
+      assert_eq!(imported.to_pgn().trim_end().ends_with("Nc6 1-0"), true);
+      assert_eq!(
+        Game::from_pgn(&imported.to_pgn()).unwrap().to_pgn(),
+        imported.to_pgn()
+      );
+
+      let pgn = "[Termination \"time forfeit\"]\n\n1. e4 e5 0-1";
+      let imported = Game::from_pgn(pgn).unwrap();
+      assert_eq!(imported.get_result(), Some(GameResult::BlackWins));
+      assert_eq!(imported.get_termination(), Some(Termination::Timeout));
+      let imported = Game::from_pgn("1. e4 e5 1/2-1/2").unwrap();
+      assert_eq!(imported.get_termination(), Some(Termination::Agreement));
+      let imported = Game::from_pgn("1. e4 e5 *").unwrap();
+      assert_eq!(imported.get_result(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_pgn() {
+      let err = |pgn: &str| Game::from_pgn(pgn).unwrap_err();
+      assert_eq!(
+        err("1. e4 e5 2. Ke3"),
+        PgnError::IllegalMove {
+          ply: 3,
+          san: String::from("Ke3")
+        }
+      );
+      assert_eq!(
+        err("1. e4 e5 2. Nf3 (2. Nc3"),
+        PgnError::UnbalancedVariation
+      );
+      assert_eq!(err("1. e4 {unfinished"), PgnError::UnterminatedComment);
+      assert_eq!(
+        err("[Event Chess]\n1. e4"),
+        PgnError::InvalidTag(String::from("[Event Chess]"))
+      );
+      assert_eq!(
+        err("[SetUp \"1\"]\n[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n*"),
+        PgnError::InvalidFen(FenError::IllegalPosition(PositionError::MissingKing(
+          Colour::White
+        )))
+      );
+    }
+  }
+
+  mod san {
+    use crate::{Game, GameState};
+
+    #[test]
+    fn makes_san_moves() {
+      let mut game = Game::new();
+      for san in ["e4", "d5", "exd5", "Nf6", "Nf3", "Nxd5", "Be2", "e6"].iter() {
+        assert_eq!(game.make_san_move(san).is_some(), true);
+      }
+      assert_eq!(game.make_san_move("O-O"), Some(GameState::InProgress));
+      assert_eq!(
+        game.to_fen(),
+        "rnbqkb1r/ppp2ppp/4p3/3n4/8/5N2/PPPPBPPP/RNBQ1RK1 b kq - 1 5"
+      );
+      // pawns can't capture without saying so, and moves have to be legal
+      assert_eq!(game.make_san_move("d5"), None);
+      assert_eq!(game.make_san_move("Ke6"), None);
+      assert_eq!(game.make_san_move("O-O"), None);
+      assert_eq!(game.make_san_move("Qd9"), None);
+      assert_eq!(game.make_san_move("hello"), None);
+      assert_eq!(game.make_san_move(""), None);
+
+      let mut game = Game::new_from_fen(String::from("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+      assert_eq!(game.make_san_move("b8"), None);
+      assert_eq!(game.make_san_move("b8=K"), None);
+      assert_eq!(game.make_san_move("b8=Q+"), Some(GameState::Check));
+      assert_eq!(game.moves, vec![String::from("b8=Q+")]);
+
+      let mut game = Game::new_from_fen(String::from("4k3/1P5p/8/8/8/8/8/4K3 w - - 0 1"));
+      assert_eq!(game.make_san_move("b8N"), Some(GameState::InProgress));
+      assert_eq!(game.moves, vec![String::from("b8=N")]);
+    }
+
+    #[test]
+    fn disambiguates_san_input() {
+      let mut game = Game::new_from_fen(String::from("k7/8/8/8/8/8/8/1N3N1K w - - 0 1"));
+      assert_eq!(game.make_san_move("Nd2"), None);
+      assert_eq!(game.make_san_move("Nbd2").is_some(), true);
+      assert_eq!(game.moves, vec![String::from("Nbd2")]);
+
+      let mut game = Game::new_from_fen(String::from("7k/8/8/R7/8/8/8/R5K1 w - - 0 1"));
+      assert_eq!(game.make_san_move("Raa3"), None);
+      assert_eq!(game.make_san_move("R1a3").is_some(), true);
+
+      let mut game = Game::new_from_fen(String::from("6k1/8/8/8/8/Q7/8/Q1Q4K w - - 0 1"));
+      assert_eq!(game.make_san_move("Qab2"), None);
+      assert_eq!(game.make_san_move("Q1b2"), None);
+      assert_eq!(game.make_san_move("Qa1b2").is_some(), true);
+
+      // more than needed is fine, the pinned knight can't move anyway
+      let mut game = Game::new_from_fen(String::from("k7/8/8/8/8/8/8/1N2rN1K w - - 0 1"));
+      assert_eq!(game.make_san_move("Nfd2"), None);
+      assert_eq!(game.make_san_move("Nb1d2").is_some(), true);
+      assert_eq!(game.moves, vec![String::from("Nd2")]);
+    }
+
+    #[test]
+    fn renders_san() {
+      let game = Game::new_from_fen(String::from(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+      ));
+      let san = |from: &str, to: &str| game.to_san(String::from(from), String::from(to));
+      assert_eq!(san("h5", "f7"), Some(String::from("Qxf7#")));
+      assert_eq!(san("c4", "f7"), Some(String::from("Bxf7+")));
+      assert_eq!(san("e1", "e2"), Some(String::from("Ke2")));
+      assert_eq!(san("e1", "g1"), None);
+      assert_eq!(san("e1", "e3"), None);
+      // rendering a move leaves the game as it was
+      assert_eq!(game.moves.len(), 0);
+      assert_eq!(game.get_game_state(), &GameState::InProgress);
+
+      let game = Game::new_from_fen(String::from("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+      // a pawn given as tiles becomes a queen
+      let san = game.to_san(String::from("b7"), String::from("b8"));
+      assert_eq!(san, Some(String::from("b8=Q+")));
+    }
+  }
+
+  mod moves {
+    use crate::{Colour, Game, GameState, Move, MoveFlags, Piece, PieceKind, Square};
+
+    fn square(name: &str) -> Square {
+      Square::from_name(name).unwrap()
+    }
+
+    #[test]
+    fn makes_squares() {
+      assert_eq!(Square::new(4, 3), Some(square("e4")));
+      assert_eq!(square("e4").get_file(), 4);
+      assert_eq!(square("e4").get_rank(), 3);
+      assert_eq!(square("h8").to_string(), "h8");
+      assert_eq!(Square::new(8, 0), None);
+      assert_eq!(Square::new(0, 8), None);
+      for name in ["", "e", "e9", "i1", "e44", "4e"].iter() {
+        assert_eq!(Square::from_name(name), None);
+      }
+      let all = Square::all();
+      assert_eq!(all.len(), 64);
+      assert_eq!((all[0], all[63]), (square("a1"), square("h8")));
+    }
+
+    #[test]
+    fn bad_tiles_are_not_moves() {
+      let mut game = Game::new();
+      assert_eq!(game.make_move(String::from("z9"), String::from("e4")), None);
+      assert_eq!(game.make_move(String::from("e2"), String::from("")), None);
+      assert_eq!(game.get_possible_moves(String::from("e")), None);
+      assert_eq!(game.get_possible_moves(String::from("e22")), None);
+      assert_eq!(game.to_san(String::from("e2"), String::from("x")), None);
+    }
+
+    #[test]
+    fn gets_legal_moves() {
+      let game = Game::new();
+      assert_eq!(game.get_all_legal_moves().len(), 20);
+      assert_eq!(game.get_legal_moves(square("e4")), vec![]);
+      let moves = game.get_legal_moves(square("e2"));
+      assert_eq!(moves.len(), 2);
+      let double_step = moves.iter().find(|mv| mv.to == square("e4")).unwrap();
+      assert_eq!(
+        double_step.flags,
+        MoveFlags {
+          double_step: true,
+          ..MoveFlags::default()
+        }
+      );
+
+      let game = Game::new_from_fen(String::from("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1"));
+      let moves = game.get_all_legal_moves();
+      let flags = |uci: &str| {
+        let mv = Move::from_uci(uci).unwrap();
+        moves
+          .iter()
+          .find(|legal| (legal.from, legal.to, legal.promotion) == (mv.from, mv.to, mv.promotion))
+          .map(|legal| legal.flags)
+      };
+      assert_eq!(flags("e1g1").unwrap().castling, true);
+      assert_eq!(flags("e1c1").unwrap().castling, true);
+      assert_eq!(flags("e5d6").unwrap().en_passant, true);
+      assert_eq!(flags("e5d6").unwrap().capture, true);
+      assert_eq!(flags("b7a8n").unwrap().capture, true);
+      assert_eq!(flags("b7b8q"), Some(MoveFlags::default()));
+      assert_eq!(flags("b7b8"), None);
+      assert_eq!(game.get_legal_moves(square("b7")).len(), 8);
+      assert_eq!(game.get_en_passant_square(), Some(square("d6")));
+    }
+
+    #[test]
+    fn makes_typed_moves() {
+      let mut game = Game::new();
+      let mv = Move::new(square("g1"), square("f3"), None);
+      assert_eq!(game.move_to_san(&mv), Some(String::from("Nf3")));
+      assert_eq!(game.apply_move(&mv), Some(GameState::InProgress));
+      assert_eq!(game.apply_move(&mv), None);
+      assert_eq!(game.get_en_passant_square(), None);
+
+      let mut game = Game::new_from_fen(String::from("4k3/1P5p/8/8/8/8/8/4K3 w - - 0 1"));
+      let promotion = Move::new(square("b7"), square("b8"), Some(PieceKind::Queen));
+      assert_eq!(game.move_to_san(&promotion), Some(String::from("b8=Q+")));
+      assert_eq!(
+        game.move_to_san(&Move::new(square("b7"), square("b8"), None)),
+        None
+      );
+      let promotion = Move::new(square("b7"), square("b8"), Some(PieceKind::Bishop));
+      assert_eq!(game.apply_move(&promotion), Some(GameState::InProgress));
+      assert_eq!(game.board[7][1], Some(Piece::Bishop(Colour::White)));
+      assert_eq!(game.moves, vec![String::from("b8=B")]);
+    }
+
+    #[test]
+    fn parses_uci_moves() {
+      for uci in ["e2e4", "e1g1", "e7e8q", "a2a1n", "h7h8r", "b2b1b"].iter() {
+        assert_eq!(Move::from_uci(uci).unwrap().to_string(), *uci);
+      }
+      for uci in [
+        "", "e2", "e2e", "e2e9", "i2e4", "e7e8k", "e7e8Q", "e7e8qq", "ée2e4",
+      ]
+      .iter()
+      {
+        assert_eq!(Move::from_uci(uci), None);
+      }
+    }
+
+    #[test]
+    fn makes_uci_moves() {
+      let mut game = Game::new();
+      for uci in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6"].iter() {
+        assert_eq!(game.make_uci_move(uci), Some(GameState::InProgress));
+      }
+      assert_eq!(game.make_uci_move("e1g1"), Some(GameState::InProgress));
+      assert_eq!(game.moves.last(), Some(&String::from("O-O")));
+      assert_eq!(game.make_uci_move("e8g8"), None);
+      assert_eq!(game.make_uci_move("e1g1"), None);
+      assert_eq!(game.make_uci_move("a7a5q"), None);
+    }
+
+    #[test]
+    fn promotes_atomically() {
+      let fen = "4k3/1P5p/8/8/8/8/8/4K3 w - - 0 1";
+      // nothing happens without a promotion piece
+      let mut game = Game::new_from_fen(String::from(fen));
+      assert_eq!(game.make_uci_move("b7b8"), None);
+      assert_eq!(game.to_fen(), fen);
+      assert_eq!(game.make_uci_move("b7b8k"), None);
+      assert_eq!(game.make_uci_move("b7b8r"), Some(GameState::Check));
+      assert_eq!(game.moves, vec![String::from("b8=R+")]);
+      assert_eq!(game.to_fen(), "1R2k3/7p/8/8/8/8/8/4K3 b - - 0 1");
+
+      // the state is worked out with the promoted piece on the board
+      let mut game = Game::new_from_fen(String::from("k7/2P5/1K6/8/8/8/8/8 w - - 0 1"));
+      assert_eq!(game.make_uci_move("c7c8q"), Some(GameState::Checkmate));
+      assert_eq!(game.moves, vec![String::from("c8=Q#")]);
+      let mut game = Game::new_from_fen(String::from("k7/2P5/1K6/8/8/8/8/8 w - - 0 1"));
+      assert_eq!(game.make_uci_move("c7c8r"), Some(GameState::Checkmate));
+      let mut game = Game::new_from_fen(String::from("k7/2P5/1K6/8/8/8/8/8 w - - 0 1"));
+      assert_eq!(
+        game.make_uci_move("c7c8n"),
+        Some(GameState::InsufficientMaterial)
+      );
+    }
+  }
+
+  mod undo {
+    use crate::{Colour, Game, GameResult, GameState, Termination};
+
+    // makes the moves and then takes them back one by one, checking every position on the way
+    fn undo_all(fen: &str, moves: &[&str]) -> Game {
+      let mut game = Game::new_from_fen(String::from(fen));
+      let mut fens = vec![game.to_fen()];
+      let mut states = vec![game.state];
+      for uci in moves.iter() {
+        assert_eq!(game.make_uci_move(uci).is_some(), true);
+        fens.push(game.to_fen());
+        states.push(game.state);
+      }
+      while game.undo() {
+        fens.pop();
+        states.pop();
+        assert_eq!(game.to_fen(), *fens.last().unwrap());
+        assert_eq!(game.state, *states.last().unwrap());
+        assert_eq!(game.moves.len(), fens.len() - 1);
+        assert_eq!(game.history.len(), fens.len());
+      }
+      assert_eq!(fens.len(), 1);
+      game
+    }
+
+    #[test]
+    fn undoes_moves() {
+      let mut game = Game::new();
+      assert_eq!(game.undo(), false);
+      assert_eq!(game.redo(), false);
+
+      // captures, castling on both sides and en passant
+      undo_all(
+        "r3k2r/pppq1ppp/2npbn2/2b1p3/2B1P3/2NPBN2/PPPQ1PPP/R3K2R w KQkq - 4 8",
+        &[
+          "e1g1", "e8c8", "c4e6", "d7e6", "a2a4", "b7b5", "a4b5", "h7h5", "b5b6", "a7b6",
+        ],
+      );
+      undo_all(
+        "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1",
+        &["e2e4", "d4e3", "e1e2"],
+      );
+      // promotions, with and without a capture
+      undo_all(
+        "1n2k3/P5P1/8/8/8/8/8/4K3 w - - 0 1",
+        &["a7b8n", "e8f7", "g7g8r"],
+      );
+    }
+
+    #[test]
+    fn undoes_game_endings() {
+      let mut game = Game::new();
+      for uci in ["f2f3", "e7e5", "g2g4", "d8h4"].iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(game.state, GameState::Checkmate);
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.state, GameState::InProgress);
+      assert_eq!(game.make_uci_move("d8g5"), Some(GameState::InProgress));
+
+      let mut game = Game::new();
+      for _ in 0..2 {
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+          game.make_uci_move(uci);
+        }
+      }
+      assert_eq!(game.state, GameState::ThreefoldRepetition);
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.get_repetition_count(), 2);
+      assert_eq!(game.state, GameState::InProgress);
+      assert_eq!(game.redo(), true);
+      assert_eq!(game.state, GameState::ThreefoldRepetition);
+    }
+
+    #[test]
+    fn keeps_resignations_and_agreements() {
+      let mut game = Game::new();
+      game.make_uci_move("e2e4");
+      assert_eq!(game.resign(Colour::White), true);
+      assert_eq!(game.undo(), false);
+      assert_eq!(game.state, GameState::GameOver);
+      assert_eq!(game.get_result(), Some(GameResult::BlackWins));
+      assert_eq!(game.moves, vec!["e4"]);
+
+      let mut game = Game::new();
+      game.offer_draw(Colour::White);
+      game.make_uci_move("e2e4");
+      assert_eq!(game.accept_draw(), true);
+      assert_eq!(game.undo(), false);
+      assert_eq!(game.get_termination(), Some(Termination::Agreement));
+    }
+
+    #[test]
+    fn redoes_moves() {
+      let mut game = Game::new();
+      for uci in ["e2e4", "e7e5", "g1f3"].iter() {
+        game.make_uci_move(uci);
+      }
+      let fen = game.to_fen();
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.redo(), true);
+      assert_eq!(game.redo(), true);
+      assert_eq!(game.redo(), false);
+      assert_eq!(game.to_fen(), fen);
+      assert_eq!(game.moves, vec!["e4", "e5", "Nf3"]);
+
+      // a new move forgets what was taken back
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.make_uci_move("b1c3").is_some(), true);
+      assert_eq!(game.redo(), false);
+      assert_eq!(game.moves, vec!["e4", "e5", "Nc3"]);
+    }
+  }
+
+  mod result {
+    use crate::{Colour, Game, GameResult, GameState, Termination};
+
+    fn outcome(game: &Game) -> Option<(GameResult, Termination)> {
+      match (game.get_result(), game.get_termination()) {
+        (Some(result), Some(termination)) => Some((result, termination)),
+        (None, None) => None,
+        _ => panic!("a result without a termination or the other way around"),
+      }
+    }
+
+    #[test]
+    fn results_from_the_board() {
+      let mut game = Game::new();
+      assert_eq!(outcome(&game), None);
+      for uci in ["f2f3", "e7e5", "g2g4"].iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(outcome(&game), None);
+      game.make_uci_move("d8h4");
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::BlackWins, Termination::Checkmate))
+      );
+      assert_eq!(game.undo(), true);
+      assert_eq!(outcome(&game), None);
+
+      let mut game = Game::new_from_fen(String::from("k7/2P5/1K6/8/8/8/8/8 w - - 0 1"));
+      game.make_uci_move("c7c8q");
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::WhiteWins, Termination::Checkmate))
+      );
+
+      let fens = [
+        ("k7/8/1Q6/8/8/8/8/K7 b - - 0 1", Termination::Stalemate),
+        (
+          "k7/8/8/8/8/8/8/K6N w - - 0 1",
+          Termination::InsufficientMaterial,
+        ),
+        (
+          "k7/8/8/8/8/8/8/K6R w - - 150 80",
+          Termination::FiftyMoveRule,
+        ),
+      ];
+      for (fen, termination) in fens.iter() {
+        let game = Game::new_from_fen(String::from(*fen));
+        assert_eq!(outcome(&game), Some((GameResult::Draw, *termination)));
+      }
+    }
+
+    #[test]
+    fn results_from_claims() {
+      let mut game = Game::new_from_fen(String::from("k7/8/8/8/8/8/8/K6R w - - 99 80"));
+      game.make_uci_move("h1h2");
+      assert_eq!(outcome(&game), None);
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::Draw, Termination::FiftyMoveRule))
+      );
+
+      let mut game = Game::new();
+      for _ in 0..2 {
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+          game.make_uci_move(uci);
+        }
+      }
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::Draw, Termination::Repetition))
+      );
+      assert_eq!(game.to_pgn().ends_with(" 1/2-1/2\n"), true);
+      assert_eq!(game.make_uci_move("g1f3"), None);
+    }
+
+    #[test]
+    fn resigns() {
+      let mut game = Game::new();
+      game.make_uci_move("e2e4");
+      assert_eq!(game.resign(Colour::Black), true);
+      assert_eq!(game.state, GameState::GameOver);
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::WhiteWins, Termination::Resignation))
+      );
+      assert_eq!(game.make_uci_move("e7e5"), None);
+      assert_eq!(game.resign(Colour::White), false);
+      assert_eq!(game.to_pgn().ends_with("1. e4 1-0\n"), true);
+
+      let mut game = Game::new();
+      assert_eq!(game.resign(Colour::White), true);
+      assert_eq!(game.get_result(), Some(GameResult::BlackWins));
+    }
+
+    #[test]
+    fn agrees_to_draws() {
+      let mut game = Game::new();
+      assert_eq!(game.accept_draw(), false);
+      assert_eq!(game.decline_draw(), false);
+
+      assert_eq!(game.offer_draw(Colour::White), true);
+      assert_eq!(game.get_draw_offer(), Some(Colour::White));
+      assert_eq!(game.decline_draw(), true);
+      assert_eq!(game.get_draw_offer(), None);
+
+      // an offer made along with a move stands until the other side has moved
+      game.offer_draw(Colour::White);
+      game.make_uci_move("e2e4");
+      assert_eq!(game.get_draw_offer(), Some(Colour::White));
+      game.make_uci_move("e7e5");
+      assert_eq!(game.get_draw_offer(), None);
+      assert_eq!(game.accept_draw(), false);
+
+      game.offer_draw(Colour::White);
+      game.make_uci_move("g1f3");
+      assert_eq!(game.undo(), true);
+      assert_eq!(game.get_draw_offer(), Some(Colour::White));
+      // a side can't accept its own offer
+      assert_eq!(game.accept_draw(), false);
+      assert_eq!(game.get_draw_offer(), Some(Colour::White));
+      game.make_uci_move("g1f3");
+      assert_eq!(game.accept_draw(), true);
+      assert_eq!(game.state, GameState::GameOver);
+      assert_eq!(
+        outcome(&game),
+        Some((GameResult::Draw, Termination::Agreement))
+      );
+      assert_eq!(game.get_draw_offer(), None);
+      assert_eq!(game.offer_draw(Colour::Black), false);
+      assert_eq!(game.make_uci_move("b8c6"), None);
+    }
+
+    #[test]
+    fn results_in_pgn() {
+      assert_eq!(GameResult::WhiteWins.to_string(), "1-0");
+      assert_eq!(GameResult::BlackWins.to_string(), "0-1");
+      assert_eq!(GameResult::Draw.to_string(), "1/2-1/2");
+    }
+  }
+
+  mod clock {
+    use crate::{
+      Clock, Colour, Game, GameResult, GameState, ManualTime, Termination, TimeBonus, TimeControl,
+      TimePeriod,
+    };
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn timed_game(fen: &str, control: TimeControl) -> (Game, Arc<ManualTime>) {
+      let time = Arc::new(ManualTime::new());
+      let mut game = Game::new_from_fen(String::from(fen));
+      game.set_clock(Clock::with_time_source(control, time.clone()));
+      (game, time)
+    }
+
+    fn time_left(game: &Game) -> (Duration, Duration) {
+      let clock = game.get_clock().unwrap();
+      (
+        clock.get_time_left(Colour::White),
+        clock.get_time_left(Colour::Black),
+      )
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn sudden_death() {
+      let (mut game, time) = timed_game(START, TimeControl::sudden_death(Duration::from_secs(60)));
+      // the clock starts with the first move
+      time.advance(Duration::from_secs(5));
+      game.make_uci_move("e2e4");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(60), Duration::from_secs(60))
+      );
+      time.advance(Duration::from_secs(20));
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(60), Duration::from_secs(40))
+      );
+      game.make_uci_move("e7e5");
+      time.advance(Duration::from_secs(15));
+      game.make_uci_move("g1f3");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(45), Duration::from_secs(40))
+      );
+      assert_eq!(game.get_clock().unwrap().get_flagged(), None);
+    }
+
+    #[test]
+    fn time_bonuses() {
+      // black takes ten seconds and then three
+      let controls = [
+        (
+          TimeControl::increment(Duration::from_secs(60), Duration::from_secs(2)),
+          Duration::from_secs(52),
+          Duration::from_secs(51),
+        ),
+        (
+          TimeControl::bronstein(Duration::from_secs(60), Duration::from_secs(5)),
+          Duration::from_secs(55),
+          Duration::from_secs(55),
+        ),
+        (
+          TimeControl::simple_delay(Duration::from_secs(60), Duration::from_secs(5)),
+          Duration::from_secs(55),
+          Duration::from_secs(55),
+        ),
+      ];
+      for (control, after_long, after_short) in controls.iter() {
+        let (mut game, time) = timed_game(START, control.clone());
+        game.make_uci_move("e2e4");
+        time.advance(Duration::from_secs(10));
+        game.make_uci_move("e7e5");
+        assert_eq!(time_left(&game).1, *after_long);
+        game.make_uci_move("g1f3");
+        time.advance(Duration::from_secs(3));
+        game.make_uci_move("b8c6");
+        assert_eq!(time_left(&game).1, *after_short);
+      }
+
+      // the delay is used up before the clock counts down
+      let (mut game, time) = timed_game(
+        START,
+        TimeControl::simple_delay(Duration::from_secs(60), Duration::from_secs(5)),
+      );
+      game.make_uci_move("e2e4");
+      time.advance(Duration::from_secs(4));
+      assert_eq!(time_left(&game).1, Duration::from_secs(60));
+      time.advance(Duration::from_secs(3));
+      assert_eq!(time_left(&game).1, Duration::from_secs(58));
+    }
+
+    #[test]
+    fn time_periods() {
+      let control = TimeControl::new(vec![
+        TimePeriod {
+          moves: Some(2),
+          time: Duration::from_secs(10),
+          bonus: TimeBonus::None,
+        },
+        TimePeriod {
+          moves: None,
+          time: Duration::from_secs(5),
+          bonus: TimeBonus::Increment(Duration::from_secs(1)),
+        },
+      ])
+      .unwrap();
+      assert_eq!(TimeControl::new(vec![]), None);
+
+      let (mut game, time) = timed_game(START, control);
+      for uci in ["e2e4", "e7e5", "g1f3"].iter() {
+        time.advance(Duration::from_secs(1));
+        game.make_uci_move(uci);
+      }
+      // the second move ends the period, and the time of the next one is added
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(14), Duration::from_secs(9))
+      );
+      time.advance(Duration::from_secs(1));
+      game.make_uci_move("b8c6");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(14), Duration::from_secs(13))
+      );
+      time.advance(Duration::from_secs(1));
+      game.make_uci_move("f1c4");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(14), Duration::from_secs(13))
+      );
+      time.advance(Duration::from_secs(2));
+      game.make_uci_move("g8f6");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(14), Duration::from_secs(12))
+      );
+    }
+
+    #[test]
+    fn loses_on_time() {
+      let (mut game, time) = timed_game(START, TimeControl::sudden_death(Duration::from_secs(60)));
+      game.make_uci_move("e2e4");
+      time.advance(Duration::from_secs(61));
+      assert_eq!(game.get_clock().unwrap().get_flagged(), Some(Colour::Black));
+      assert_eq!(game.make_uci_move("e7e5"), None);
+      assert_eq!(game.state, GameState::GameOver);
+      assert_eq!(game.get_result(), Some(GameResult::WhiteWins));
+      assert_eq!(game.get_termination(), Some(Termination::Timeout));
+      assert_eq!(game.check_time(), false);
+
+      // without the pieces to mate, the other side only gets a draw
+      let fens = [
+        "kq6/8/8/8/8/8/8/K7 w - - 0 1",
+        "k7/pp6/8/8/8/8/8/K7 w - - 0 1",
+      ];
+      for fen in fens.iter() {
+        let (mut game, time) = timed_game(fen, TimeControl::sudden_death(Duration::from_secs(60)));
+        game.make_uci_move("a1a2");
+        time.advance(Duration::from_secs(60));
+        assert_eq!(game.check_time(), true);
+        assert_eq!(game.get_result(), Some(GameResult::Draw));
+        assert_eq!(game.get_termination(), Some(Termination::Timeout));
+      }
+      // a knight can mate when a pawn of the other side blocks the king
+      let fens = [
+        "k7/pp6/8/8/8/8/8/KBN5 w - - 0 1",
+        "k7/p7/8/8/8/8/8/KN6 w - - 0 1",
+      ];
+      for fen in fens.iter() {
+        let (mut game, time) = timed_game(fen, TimeControl::sudden_death(Duration::from_secs(60)));
+        game.make_uci_move("a1a2");
+        time.advance(Duration::from_secs(60));
+        assert_eq!(game.check_time(), true);
+        assert_eq!(game.get_result(), Some(GameResult::WhiteWins));
+      }
+    }
+
+    #[test]
+    fn first_move_gets_the_bonus() {
+      let (mut game, time) = timed_game(
+        START,
+        TimeControl::increment(Duration::from_secs(60), Duration::from_secs(2)),
+      );
+      game.make_uci_move("e2e4");
+      time.advance(Duration::from_secs(3));
+      game.make_uci_move("e7e5");
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(62), Duration::from_secs(59))
+      );
+    }
+
+    #[test]
+    fn undo_takes_back_the_period() {
+      let control = TimeControl::new(vec![TimePeriod {
+        moves: Some(2),
+        time: Duration::from_secs(10),
+        bonus: TimeBonus::None,
+      }])
+      .unwrap();
+      let (mut game, _) = timed_game(START, control);
+      for uci in ["e2e4", "e7e5", "g1f3"].iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(time_left(&game).0, Duration::from_secs(20));
+      // the move that ended the period is taken back, and so is the time it brought
+      assert_eq!(game.undo(), true);
+      assert_eq!(time_left(&game).0, Duration::from_secs(10));
+      assert_eq!(game.redo(), true);
+      assert_eq!(time_left(&game).0, Duration::from_secs(20));
+      game.make_uci_move("b8c6");
+      game.make_uci_move("f1c4");
+      assert_eq!(time_left(&game).0, Duration::from_secs(20));
+      game.make_uci_move("g8f6");
+      game.make_uci_move("e1g1");
+      assert_eq!(time_left(&game).0, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn undo_takes_back_the_bonus() {
+      let (mut game, _) = timed_game(
+        START,
+        TimeControl::increment(Duration::from_secs(60), Duration::from_secs(2)),
+      );
+      game.make_uci_move("e2e4");
+      assert_eq!(time_left(&game).0, Duration::from_secs(62));
+      for _ in 0..5 {
+        assert_eq!(game.undo(), true);
+        assert_eq!(time_left(&game).0, Duration::from_secs(60));
+        assert_eq!(game.redo(), true);
+      }
+      assert_eq!(time_left(&game).0, Duration::from_secs(62));
+    }
+
+    #[test]
+    fn timed_game_can_change_threads() {
+      fn assert_send<T: Send + Sync>(_: &T) {}
+      let (game, _) = timed_game(START, TimeControl::sudden_death(Duration::from_secs(60)));
+      assert_send(&game);
+    }
+
+    #[test]
+    fn undo_hands_back_the_clock() {
+      let (mut game, time) = timed_game(START, TimeControl::sudden_death(Duration::from_secs(60)));
+      game.make_uci_move("e2e4");
+      time.advance(Duration::from_secs(10));
+      game.make_uci_move("e7e5");
+      time.advance(Duration::from_secs(10));
+      assert_eq!(game.undo(), true);
+      // white's ten seconds are gone, and now black's clock runs
+      time.advance(Duration::from_secs(5));
+      assert_eq!(
+        time_left(&game),
+        (Duration::from_secs(50), Duration::from_secs(45))
+      );
+    }
+  }
+
+  mod bitboard {
+    use crate::bitboard::{self, Bitboards};
+    use crate::{Colour, Game, Piece, Position};
+
+    fn in_sync(game: &Game) -> bool {
+      game.bitboards == Bitboards::from_board(&game.board)
+    }
+
+    #[test]
+    fn tiles_and_indices() {
+      assert_eq!(bitboard::index(&Position(0, 0)), 0);
+      assert_eq!(bitboard::index(&Position(0, 7)), 7);
+      assert_eq!(bitboard::index(&Position(7, 7)), 63);
+      assert_eq!(bitboard::position(28), Position(3, 4));
+      assert_eq!(
+        bitboard::tiles(0b1010_0001).collect::<Vec<_>>(),
+        vec![0, 5, 7]
+      );
+    }
+
+    #[test]
+    fn attacks() {
+      // a knight in the corner and one in the middle
+      assert_eq!(
+        bitboard::attacks(Piece::Knight(Colour::White), 0, 0).count_ones(),
+        2
+      );
+      assert_eq!(
+        bitboard::attacks(Piece::Knight(Colour::White), 27, 0).count_ones(),
+        8
+      );
+      assert_eq!(
+        bitboard::attacks(Piece::King(Colour::Black), 7, 0).count_ones(),
+        3
+      );
+      assert_eq!(bitboard::pawn_attacks(Colour::White, 8), 1 << 17);
+      assert_eq!(bitboard::pawn_attacks(Colour::Black, 55), 1 << 46);
+      // a rook on a1 with a piece on a4 sees a2 to a4 and the first rank
+      let rook = bitboard::attacks(Piece::Rook(Colour::White), 0, 1 << 24);
+      assert_eq!(rook, (1 << 8) | (1 << 16) | (1 << 24) | 0xfe);
+      let queen = bitboard::attacks(Piece::Queen(Colour::White), 27, 0);
+      assert_eq!(queen.count_ones(), 27);
+    }
+
+    #[test]
+    fn pawn_pushes() {
+      assert_eq!(
+        bitboard::pawn_pushes(Colour::White, 12, 0),
+        (1 << 20) | (1 << 28)
+      );
+      // a piece right in front blocks both steps
+      assert_eq!(bitboard::pawn_pushes(Colour::White, 12, 1 << 20), 0);
+      assert_eq!(bitboard::pawn_pushes(Colour::Black, 52, 1 << 36), 1 << 44);
+      assert_eq!(bitboard::pawn_pushes(Colour::Black, 44, 0), 1 << 36);
+    }
+
+    #[test]
+    fn stay_in_sync_with_the_board() {
+      let mut game = Game::new();
+      assert_eq!(in_sync(&game), true);
+      // castling, en passant and a promotion by capture
+      for uci in [
+        "e2e4", "g8f6", "e4e5", "d7d5", "e5d6", "e7e6", "d6c7", "f8e7", "c7b8q", "e8g8",
+      ]
+      .iter()
+      {
+        assert_eq!(game.make_uci_move(uci).is_some(), true);
+        assert_eq!(in_sync(&game), true);
+      }
+      while game.undo() {
+        assert_eq!(in_sync(&game), true);
+      }
+      assert_eq!(game.bitboards, Game::new().bitboards);
+    }
+
+    #[test]
+    fn finds_the_kings() {
+      let game = Game::new_from_fen(String::from("8/8/8/8/8/2k5/8/6K1 w - - 0 1"));
+      assert_eq!(game.bitboards.get_king(Colour::White), 6);
+      assert_eq!(game.bitboards.get_king(Colour::Black), 18);
+    }
+  }
+
+  mod perft {
+    use crate::{Game, Move};
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn start_position() {
+      let game = Game::new();
+      assert_eq!(game.perft(0), 1);
+      assert_eq!(game.perft(1), 20);
+      assert_eq!(game.perft(2), 400);
+      assert_eq!(game.perft(3), 8902);
+      assert_eq!(game.perft(4), 197281);
+    }
+
+    #[test]
+    fn kiwipete() {
+      let game = Game::new_from_fen(String::from(KIWIPETE));
+      assert_eq!(game.perft(1), 48);
+      assert_eq!(game.perft(2), 2039);
+      assert_eq!(game.perft(3), 97862);
+    }
+
+    #[test]
+    fn endgame_with_en_passant_pins() {
+      let game = Game::new_from_fen(String::from("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"));
+      assert_eq!(game.perft(1), 14);
+      assert_eq!(game.perft(2), 191);
+      assert_eq!(game.perft(3), 2812);
+    }
+
+    #[test]
+    fn promotions_and_castling_out_of_check() {
+      let game = Game::new_from_fen(String::from(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+      ));
+      assert_eq!(game.perft(1), 6);
+      assert_eq!(game.perft(2), 264);
+      assert_eq!(game.perft(3), 9467);
+    }
+
+    #[test]
+    fn promotion_by_capture() {
+      let game = Game::new_from_fen(String::from(
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+      ));
+      assert_eq!(game.perft(1), 44);
+      assert_eq!(game.perft(2), 1486);
+      assert_eq!(game.perft(3), 62379);
+    }
+
+    #[test]
+    fn middlegame() {
+      let game = Game::new_from_fen(String::from(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+      ));
+      assert_eq!(game.perft(1), 46);
+      assert_eq!(game.perft(2), 2079);
+    }
+
+    #[test]
+    fn checkmate_has_no_moves() {
+      let game = Game::new_from_fen(String::from(
+        "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+      ));
+      assert_eq!(game.perft(1), 0);
+      assert_eq!(game.perft(2), 0);
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+      let game = Game::new_from_fen(String::from(KIWIPETE));
+      let counts = game.divide(2);
+      assert_eq!(counts.len(), 48);
+      assert_eq!(counts.iter().map(|(_, count)| count).sum::<u64>(), 2039);
+      let castle = Move::from_uci("e1g1").unwrap();
+      assert_eq!(
+        counts
+          .iter()
+          .any(|(mv, count)| mv.from == castle.from && mv.to == castle.to && *count == 43),
+        true
+      );
+      assert_eq!(game.divide(0).is_empty(), true);
+    }
+  }
+
+  mod zobrist {
+    use crate::Game;
+
+    // the keys from the description of the Polyglot book format, with the moves leading to them
+    const POLYGLOT: [(&str, &str, u64); 9] = [
+      (
+        "",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        0x463b96181691fc9c,
+      ),
+      (
+        "e2e4",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        0x823c9b50fd114196,
+      ),
+      (
+        "d7d5",
+        "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+        0x0756b94461c50fb0,
+      ),
+      (
+        "e4e5",
+        "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+        0x662fafb965db29d4,
+      ),
+      (
+        "f7f5",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        0x22a48b5a8e47ff78,
+      ),
+      (
+        "e1e2",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+        0x652a607ca3f242c1,
+      ),
+      (
+        "e8f7",
+        "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+        0x00fdd303c946bdd9,
+      ),
+      (
+        "",
+        "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+        0x3c8123ea7b067637,
+      ),
+      (
+        "",
+        "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+        0x5c3f9b829b279560,
+      ),
+    ];
+
+    #[test]
+    fn polyglot_keys_from_fen() {
+      for (_, fen, key) in POLYGLOT.iter() {
+        assert_eq!(Game::new_from_fen(String::from(*fen)).hash(), *key);
+      }
+    }
+
+    #[test]
+    fn polyglot_keys_after_moves() {
+      let mut game = Game::new();
+      for (uci, _, key) in POLYGLOT[..7].iter() {
+        if !uci.is_empty() {
+          game.make_uci_move(uci);
+        }
+        assert_eq!(game.hash(), *key);
+      }
+
+      let mut game = Game::new();
+      for uci in ["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"].iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(game.hash(), POLYGLOT[7].2);
+      // en passant and a rook leaving its corner
+      game.make_uci_move("b4c3");
+      game.make_uci_move("a1a3");
+      assert_eq!(game.hash(), POLYGLOT[8].2);
+    }
+
+    #[test]
+    fn undo_restores_the_key() {
+      let mut game = Game::new();
+      for uci in [
+        "e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "g1f3", "c6b5", "e1g1",
+      ]
+      .iter()
+      {
+        game.make_uci_move(uci);
+        assert_eq!(game.hash(), game.compute_hash());
+      }
+      while game.undo() {
+        assert_eq!(game.hash(), game.compute_hash());
+      }
+      assert_eq!(game.hash(), POLYGLOT[0].2);
+      assert_eq!(game.redo(), true);
+      assert_eq!(game.hash(), POLYGLOT[1].2);
+    }
+
+    #[test]
+    fn transpositions_have_the_same_key() {
+      let mut first = Game::new();
+      let mut second = Game::new();
+      for uci in ["g1f3", "g8f6", "b1c3", "b8c6"].iter() {
+        first.make_uci_move(uci);
+      }
+      for uci in ["b1c3", "b8c6", "g1f3", "g8f6"].iter() {
+        second.make_uci_move(uci);
+      }
+      assert_eq!(first.hash(), second.hash());
+      // knights going back and forth is the same position again
+      for uci in ["f3g1", "f6g8", "g1f3", "g8f6"].iter() {
+        first.make_uci_move(uci);
+      }
+      assert_eq!(first.hash(), second.hash());
+    }
+
+    #[test]
+    fn side_to_move_and_castling_rights_count() {
+      let white = Game::new_from_fen(String::from("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1"));
+      let others = [
+        "4k3/8/8/8/8/8/8/R3K2R b KQ - 0 1",
+        "4k3/8/8/8/8/8/8/R3K2R w K - 0 1",
+        "4k3/8/8/8/8/8/8/R3K2R w - - 0 1",
+      ];
+      for fen in others.iter() {
+        assert_ne!(white.hash(), Game::new_from_fen(String::from(*fen)).hash());
+      }
+      // a target no pawn can take on doesn't change anything
+      let target = Game::new_from_fen(String::from("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"));
+      let no_target = Game::new_from_fen(String::from("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1"));
+      assert_eq!(target.hash(), no_target.hash());
+    }
+  }
+
+  mod search {
+    use crate::{best_move, Game, SearchLimits};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn mate_in_one() {
+      let game = Game::new_from_fen(String::from(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+      ));
+      let result = best_move(&game, SearchLimits::depth(3)).unwrap();
+      assert_eq!(result.best_move.to_string(), "h5f7");
+      assert_eq!(result.get_mate_in(), Some(1));
+    }
+
+    #[test]
+    fn mate_in_two() {
+      let game = Game::new_from_fen(String::from("k7/8/2K5/8/8/8/8/7R w - - 0 1"));
+      let result = best_move(&game, SearchLimits::depth(4)).unwrap();
+      assert_eq!(result.best_move.to_string(), "c6b6");
+      assert_eq!(result.get_mate_in(), Some(2));
+      assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn getting_mated() {
+      // black can only delay the mate by a move
+      let game = Game::new_from_fen(String::from("k7/8/1K6/8/8/8/8/6R1 b - - 0 1"));
+      let result = best_move(&game, SearchLimits::depth(3)).unwrap();
+      assert_eq!(result.best_move.to_string(), "a8b8");
+      assert_eq!(result.get_mate_in(), Some(-1));
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+      let game = Game::new_from_fen(String::from("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1"));
+      let result = best_move(&game, SearchLimits::depth(2)).unwrap();
+      assert_eq!(result.best_move.to_string(), "d1d5");
+      assert_eq!(result.score > 300, true);
+      assert_eq!(result.get_mate_in(), None);
+    }
+
+    #[test]
+    fn sees_the_recapture() {
+      // the rook is defended by the pawn, taking it loses the queen
+      let game = Game::new_from_fen(String::from("4k3/8/2p5/3r4/8/8/8/3QK3 w - - 0 1"));
+      let result = best_move(&game, SearchLimits::depth(1)).unwrap();
+      assert_ne!(result.best_move.to_string(), "d1d5");
+    }
+
+    #[test]
+    fn principal_variation_is_legal() {
+      let game = Game::new();
+      let result = best_move(&game, SearchLimits::depth(4)).unwrap();
+      assert_eq!(result.depth, 4);
+      assert_eq!(result.pv[0], result.best_move);
+      let mut line = game.clone();
+      for mv in result.pv.iter() {
+        assert_eq!(line.apply_move(mv).is_some(), true);
+      }
+      // the game searched from is left alone
+      assert_eq!(game.to_fen(), Game::new().to_fen());
+    }
+
+    #[test]
+    fn no_move_to_make() {
+      let mate = Game::new_from_fen(String::from(
+        "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+      ));
+      assert_eq!(best_move(&mate, SearchLimits::depth(3)).is_none(), true);
+      let stalemate = Game::new_from_fen(String::from("k7/8/1Q6/8/8/8/8/7K b - - 0 1"));
+      assert_eq!(
+        best_move(&stalemate, SearchLimits::depth(3)).is_none(),
+        true
+      );
+    }
+
+    #[test]
+    fn stops_at_the_limits() {
+      let game = Game::new();
+      let result = best_move(&game, SearchLimits::nodes(5000)).unwrap();
+      assert_eq!(result.nodes <= 5000, true);
+
+      // a depth of zero still gives a move
+      let result = best_move(&game, SearchLimits::depth(0)).unwrap();
+      assert_eq!(result.depth, 1);
+
+      let start = Instant::now();
+      let limits = SearchLimits {
+        time: Some(Duration::from_millis(100)),
+        ..SearchLimits::default()
+      };
+      assert_eq!(best_move(&game, limits).is_some(), true);
+      assert_eq!(start.elapsed() < Duration::from_secs(2), true);
+    }
+
+    #[test]
+    fn repetition_is_a_draw() {
+      // white is a queen down, and the knight going back repeats the position a third time
+      let mut game = Game::new_from_fen(String::from("3qk3/8/8/8/8/8/8/4K1N1 b - - 0 1"));
+      for uci in ["e8f8", "g1f3", "f8e8", "f3g1", "e8f8", "g1f3", "f8e8"].iter() {
+        game.make_uci_move(uci);
+      }
+      let result = best_move(&game, SearchLimits::depth(2)).unwrap();
+      assert_eq!(result.best_move.to_string(), "f3g1");
+      assert_eq!(result.score, 0);
+    }
+  }
+
+  mod eval {
+    use crate::{evaluate, evaluate_with, EvalWeights, Game};
+
+    #[test]
+    fn start_position_is_even() {
+      assert_eq!(evaluate(&Game::new()), 0);
+    }
+
+    #[test]
+    fn side_to_move() {
+      let white = Game::new_from_fen(String::from("4k3/8/8/8/8/8/8/3QK3 w - - 0 1"));
+      let black = Game::new_from_fen(String::from("4k3/8/8/8/8/8/8/3QK3 b - - 0 1"));
+      assert_eq!(evaluate(&white) > 800, true);
+      assert_eq!(evaluate(&black), -evaluate(&white));
+    }
+
+    #[test]
+    fn mirrored_position() {
+      let game = Game::new_from_fen(String::from(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+      ));
+      let mirrored = Game::new_from_fen(String::from(
+        "rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4",
+      ));
+      assert_eq!(evaluate(&game), evaluate(&mirrored));
+    }
+
+    #[test]
+    fn doubled_and_isolated_pawns() {
+      // only kings and pawns left, so the endgame weights count in full
+      let position = Game::new_from_fen(String::from("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1"));
+      let score = evaluate(&position);
+      let weights = EvalWeights {
+        doubled_pawn: (0, 0),
+        ..EvalWeights::default()
+      };
+      assert_eq!(score - evaluate_with(&position, &weights), -20);
+      let weights = EvalWeights {
+        isolated_pawn: (0, 0),
+        ..EvalWeights::default()
+      };
+      assert_eq!(score - evaluate_with(&position, &weights), -30);
+    }
+
+    #[test]
+    fn passed_pawns() {
+      let weights = EvalWeights {
+        passed_pawn: [(0, 0); 8],
+        ..EvalWeights::default()
+      };
+      let passed = Game::new_from_fen(String::from("4k3/8/P7/8/8/8/8/4K3 w - - 0 1"));
+      assert_eq!(evaluate(&passed) - evaluate_with(&passed, &weights), 90);
+      // the pawns stop each other
+      let blocked = Game::new_from_fen(String::from("4k3/1p6/P7/8/8/8/8/4K3 w - - 0 1"));
+      assert_eq!(evaluate(&blocked), evaluate_with(&blocked, &weights));
+    }
+
+    #[test]
+    fn king_comes_out_in_the_endgame() {
+      let centre = Game::new_from_fen(String::from("4k3/8/8/8/4K3/8/8/8 w - - 0 1"));
+      let corner = Game::new_from_fen(String::from("4k3/8/8/8/8/8/8/K7 w - - 0 1"));
+      assert_eq!(evaluate(&centre) > evaluate(&corner), true);
+      // with all the pieces on the board it stays behind its pawns
+      let castled = Game::new_from_fen(String::from(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1RK1 w kq - 0 1",
+      ));
+      let centre = Game::new_from_fen(String::from(
+        "rnbqkbnr/pppppppp/8/8/4K3/8/PPPPPPPP/RNBQ1R2 w kq - 0 1",
+      ));
+      assert_eq!(evaluate(&castled) > evaluate(&centre), true);
+    }
+
+    #[test]
+    fn default_weights() {
+      let position = Game::new_from_fen(String::from(
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+      ));
+      assert_eq!(
+        evaluate(&position),
+        evaluate_with(&position, &EvalWeights::default())
+      );
+    }
+  }
+
+  mod uci {
+    use super::run;
+    use crate::protocol::{Protocol, Signals};
+    use crate::uci::Uci;
+    use std::sync::Arc;
+
+    #[test]
+    fn handshake() {
+      let lines = run(Uci::new, &["uci", "isready"]);
+      assert_eq!(lines[0].starts_with("id name "), true);
+      assert_eq!(lines[1].starts_with("id author "), true);
+      assert_eq!(lines[lines.len() - 2], "uciok");
+      assert_eq!(lines[lines.len() - 1], "readyok");
+    }
+
+    #[test]
+    fn go_depth() {
+      let lines = run(Uci::new, &["position startpos moves e2e4", "go depth 3"]);
+      assert_eq!(lines.len(), 4);
+      assert_eq!(lines[0].starts_with("info depth 1 score cp "), true);
+      assert_eq!(lines[2].starts_with("info depth 3 "), true);
+      assert_eq!(lines[3].starts_with("bestmove "), true);
+      // the best move is black's
+      let best = lines[3].split_whitespace().nth(1).unwrap();
+      assert_eq!("78".contains(best.chars().nth(1).unwrap()), true);
+    }
 
-      for i in 0..=7 {
-        assert_eq!(game.board[1][i].unwrap(), Piece::Pawn(Colour::White));
-      }
+    #[test]
+    fn mate_score() {
+      let lines = run(
+        Uci::new,
+        &[
+          "position fen r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+          "go depth 3",
+        ],
+      );
+      assert_eq!(lines[0].contains(" score mate 1 "), true);
+      assert_eq!(lines.last().unwrap(), "bestmove h5f7");
+    }
 
-      for i in 0..=7 {
-        assert_eq!(game.board[2][i].is_none(), true);
-      }
+    #[test]
+    fn position_with_moves() {
+      let lines = run(
+        Uci::new,
+        &[
+          "position fen 7k/8/8/8/8/8/6PP/7K w - - 0 1 moves g2g4 h8g8 g4g5",
+          "go depth 1",
+        ],
+      );
+      assert_eq!(lines.last().unwrap().starts_with("bestmove g8"), true);
+    }
 
-      for i in 0..=7 {
-        assert_eq!(game.board[6][i].unwrap(), Piece::Pawn(Colour::Black));
-      }
+    #[test]
+    fn invalid_position() {
+      let lines = run(
+        Uci::new,
+        &[
+          "position startpos moves e2e4",
+          "position startpos moves e2e5",
+          "position fen 8/8/8 w - - 0 1",
+          "go depth 1",
+        ],
+      );
+      assert_eq!(lines[0], "info string invalid position startpos moves e2e5");
+      assert_eq!(lines[1], "info string invalid position fen 8/8/8 w - - 0 1");
+      // the last valid position is kept
+      let best = lines.last().unwrap().split_whitespace().nth(1).unwrap();
+      assert_eq!("78".contains(best.chars().nth(1).unwrap()), true);
+    }
 
-      assert_eq!(game.board[7][0].unwrap(), Piece::Rook(Colour::Black));
-      assert_eq!(game.board[7][1].unwrap(), Piece::Knight(Colour::Black));
-      assert_eq!(game.board[7][2].unwrap(), Piece::Bishop(Colour::Black));
-      assert_eq!(game.board[7][3].unwrap(), Piece::Queen(Colour::Black));
-      assert_eq!(game.board[7][4].unwrap(), Piece::King(Colour::Black));
-      assert_eq!(game.board[7][5].unwrap(), Piece::Bishop(Colour::Black));
-      assert_eq!(game.board[7][6].unwrap(), Piece::Knight(Colour::Black));
-      assert_eq!(game.board[7][7].unwrap(), Piece::Rook(Colour::Black));
+    #[test]
+    fn no_move_to_make() {
+      let lines = run(
+        Uci::new,
+        &["position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", "go depth 2"],
+      );
+      assert_eq!(lines, vec!["bestmove 0000"]);
+    }
 
-      let game = Game::new_from_fen(String::from("4k2r/6r1/8/8/8/8/3R4/R3K3 b Qk - 0 1"));
-      assert_eq!(game.turn, Colour::Black);
+    #[test]
+    fn time_controls() {
+      let lines = run(
+        Uci::new,
+        &[
+          "position startpos",
+          "go wtime 200 btime 200 winc 0 binc 0",
+          "go movetime 100",
+          "go nodes 1000",
+        ],
+      );
+      let moves = lines.iter().filter(|line| line.starts_with("bestmove "));
+      assert_eq!(moves.count(), 3);
     }
 
     #[test]
-    fn random_fen_inits() {
-      let game = Game::new_from_fen(String::from("4k2r/6r1/8/8/8/8/3R4/R3K3 w Qk - 0 1"));
-      assert_eq!(game.turn, Colour::White);
+    fn options() {
+      let lines = run(
+        Uci::new,
+        &[
+          "setoption name Move Overhead value 100",
+          "setoption name Move Overhead value lots",
+          "setoption name Hash value 16",
+        ],
+      );
+      assert_eq!(
+        lines,
+        vec![
+          "info string invalid value for Move Overhead",
+          "info string unknown option Hash",
+        ]
+      );
+    }
 
-      assert_eq!(game.board[0][0].unwrap(), Piece::Rook(Colour::White));
-      assert_eq!(game.board[1][3].unwrap(), Piece::Rook(Colour::White));
-      assert_eq!(game.board[6][6].unwrap(), Piece::Rook(Colour::Black));
-      assert_eq!(game.board[7][7].unwrap(), Piece::Rook(Colour::Black));
-      assert_eq!(game.board[7][6].is_none(), true);
-      assert_eq!(game.board[0][4].unwrap(), Piece::King(Colour::White));
-      assert_eq!(game.board[7][4].unwrap(), Piece::King(Colour::Black));
-      assert_eq!(game.board[0][5].is_none(), true);
+    #[test]
+    fn quit() {
+      let mut engine = Uci::new(Arc::new(Signals::default()));
+      let mut out = vec![];
+      assert_eq!(engine.handle("ucinewgame", &mut out), true);
+      assert_eq!(engine.handle("stop", &mut out), true);
+      assert_eq!(engine.handle("quit", &mut out), false);
+      assert_eq!(out.is_empty(), true);
+    }
+  }
 
-      let game = Game::new_from_fen(String::from(
-        "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50",
-      ));
-      assert_eq!(game.turn, Colour::Black);
+  mod xboard {
+    use super::run;
+    use crate::protocol::{Protocol, Signals};
+    use crate::xboard::XBoard;
+    use std::sync::Arc;
 
-      assert_eq!(game.board[2][0].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[4][1].unwrap(), Piece::Pawn(Colour::Black));
-      assert_eq!(game.board[3][1].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[3][0].unwrap(), Piece::Pawn(Colour::Black));
-      assert_eq!(game.board[5][3].unwrap(), Piece::Pawn(Colour::Black));
-      assert_eq!(game.board[4][4].unwrap(), Piece::Pawn(Colour::Black));
-      assert_eq!(game.board[3][5].unwrap(), Piece::Pawn(Colour::Black));
-      assert_eq!(game.board[4][3].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[3][4].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[2][5].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[3][7].unwrap(), Piece::Pawn(Colour::White));
-      assert_eq!(game.board[4][7].unwrap(), Piece::Pawn(Colour::Black));
+    #[test]
+    fn features() {
+      let lines = run(
+        XBoard::new,
+        &["xboard", "protover 2", "accepted ping", "ping 7"],
+      );
+      assert_eq!(lines.len(), 2);
+      assert_eq!(lines[0].starts_with("feature "), true);
+      assert_eq!(lines[0].contains(" usermove=1 "), true);
+      assert_eq!(lines[0].ends_with(" done=1"), true);
+      assert_eq!(lines[1], "pong 7");
+    }
 
-      assert_eq!(game.board[2][7].unwrap(), Piece::King(Colour::White));
-      assert_eq!(game.board[6][5].unwrap(), Piece::King(Colour::Black));
+    #[test]
+    fn answers_a_move() {
+      let lines = run(XBoard::new, &["new", "sd 2", "usermove e2e4"]);
+      assert_eq!(lines.len(), 1);
+      assert_eq!(lines[0].starts_with("move "), true);
+      // the moves are read the same way as in the terminal game
+      let lines = run(XBoard::new, &["new", "sd 2", "usermove Nf3"]);
+      assert_eq!(lines[0].starts_with("move "), true);
+    }
 
-      assert_eq!(game.board[0][0].is_none(), true);
-      assert_eq!(game.board[7][7].is_none(), true);
-      assert_eq!(game.board[5][7].is_none(), true);
+    #[test]
+    fn force_mode() {
+      let lines = run(
+        XBoard::new,
+        &[
+          "new",
+          "force",
+          "usermove e2e4",
+          "usermove e7e5",
+          "sd 1",
+          "go",
+        ],
+      );
+      assert_eq!(lines.len(), 1);
+      // go makes the engine play the side to move
+      assert_eq!("12".contains(&lines[0][6..7]), true);
     }
 
     #[test]
-    fn kings_are_in_place() {
-      let game = Game::new();
-      assert_eq!(
-        game.board[7][4].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::King(Colour::Black)
+    fn illegal_moves() {
+      let lines = run(
+        XBoard::new,
+        &["new", "force", "usermove e2e5", "e7e5", "foo"],
       );
       assert_eq!(
-        game.board[0][4].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::King(Colour::White)
+        lines,
+        vec![
+          "Illegal move: e2e5",
+          "Illegal move: e7e5",
+          "Error (unknown command): foo",
+        ]
       );
     }
+
     #[test]
-    fn queens_are_in_place() {
-      let game = Game::new();
-      assert_eq!(
-        game.board[7][3].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::Queen(Colour::Black)
-      );
-      assert_eq!(
-        game.board[0][3].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::Queen(Colour::White)
+    fn mates_and_sends_the_result() {
+      let lines = run(
+        XBoard::new,
+        &[
+          "new",
+          "post",
+          "sd 3",
+          "setboard r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+          "go",
+        ],
       );
+      assert_eq!(lines[0].starts_with("1 100001 "), true);
+      assert_eq!(lines[lines.len() - 2], "move h5f7");
+      assert_eq!(lines[lines.len() - 1], "1-0 {White mates}");
     }
 
     #[test]
-    fn rooks_are_in_place() {
-      let game = Game::new();
-      assert_eq!(
-        game.board[0][0].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::Rook(Colour::White)
+    fn user_ends_the_game() {
+      let lines = run(
+        XBoard::new,
+        &["setboard 7k/5Q2/6K1/8/8/8/8/8 w - - 0 1", "usermove f7g7"],
       );
-      assert_eq!(
-        game.board[7][7].unwrap_or(Piece::Pawn(Colour::Black)),
-        Piece::Rook(Colour::Black)
+      assert_eq!(lines, vec!["1-0 {White mates}"]);
+      let lines = run(
+        XBoard::new,
+        &["setboard 7k/5Q2/6K1/8/8/8/8/8 w - - 0 1", "usermove g6h6"],
       );
+      assert_eq!(lines, vec!["1/2-1/2 {Stalemate}"]);
     }
 
     #[test]
-    fn nones_in_place() {
-      let game = Game::new();
-      assert_eq!(game.board[3][5].is_none(), true);
-      assert_eq!(game.board[4][2].is_none(), true);
+    fn setboard_and_undo() {
+      let lines = run(
+        XBoard::new,
+        &[
+          "force",
+          "setboard 8/8/8/8",
+          "usermove e2e4",
+          "usermove e7e5",
+          // e2e4 can only be played again once it is taken back
+          "remove",
+          "usermove e2e4",
+          "undo",
+          "usermove e2e4",
+          "usermove e7e5",
+          "undo",
+          "usermove e7e5",
+        ],
+      );
+      assert_eq!(lines, vec!["tellusererror Illegal position"]);
     }
 
     #[test]
-    fn gets_game_state() {
-      let mut game = Game::new();
-      assert_eq!(*game.get_game_state(), GameState::InProgress);
-      game.state = GameState::GameOver;
-      assert_eq!(*game.get_game_state(), GameState::GameOver);
+    fn time_controls() {
+      let lines = run(
+        XBoard::new,
+        &[
+          "new",
+          "level 40 0:30 0",
+          "time 100",
+          "otim 100",
+          "usermove e2e4",
+          "st 1",
+          "usermove g1f3",
+          "level 0 5",
+        ],
+      );
+      assert_eq!(lines.len(), 3);
+      assert_eq!(lines[0].starts_with("move "), true);
+      assert_eq!(lines[1].starts_with("move "), true);
+      assert_eq!(lines[2], "Error (bad level): level 0 5");
+    }
+
+    #[test]
+    fn quit() {
+      let mut engine = XBoard::new(Arc::new(Signals::default()));
+      let mut out = vec![];
+      assert_eq!(engine.handle("nopost", &mut out), true);
+      assert_eq!(engine.handle("quit", &mut out), false);
+      assert_eq!(out.is_empty(), true);
     }
   }
 
@@ -149,9 +2080,9 @@ mod tests {
     #[test]
     fn parsing_positions() {
       //let game = Game::new();
-      assert_eq!(Game::parse_string(&String::from("a5")), Position(4, 0));
-      assert_eq!(Game::parse_string(&String::from("d7")), Position(6, 3));
-      assert_eq!(Game::parse_string(&String::from("h7")), Position(6, 7));
+      assert_eq!(Game::parse_tile("a5"), Some(Position(4, 0)));
+      assert_eq!(Game::parse_tile("d7"), Some(Position(6, 3)));
+      assert_eq!(Game::parse_tile("h7"), Some(Position(6, 7)));
     }
 
     #[test]
@@ -565,6 +2496,7 @@ mod tests {
     use crate::Game;
     use crate::GameState;
     use crate::Piece;
+    use crate::Termination;
     #[test]
     fn castling() {
       let game = Game::new();
@@ -596,6 +2528,120 @@ mod tests {
       assert_eq!(game.castling, castling);
     }
 
+    #[test]
+    fn castling_moves() {
+      let mut game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+      let mut m = game.get_possible_moves(String::from("e1")).unwrap();
+      let mut m2 = vec![
+        String::from("c1"),
+        String::from("d1"),
+        String::from("d2"),
+        String::from("e2"),
+        String::from("f2"),
+        String::from("f1"),
+        String::from("g1"),
+      ];
+      m.sort();
+      m2.sort();
+      assert_eq!(m, m2);
+
+      game.make_move(String::from("e1"), String::from("g1"));
+      assert_eq!(game.board[0][6].unwrap(), Piece::King(Colour::White));
+      assert_eq!(game.board[0][5].unwrap(), Piece::Rook(Colour::White));
+      assert_eq!(game.board[0][7].is_none(), true);
+      assert_eq!(game.board[0][4].is_none(), true);
+      assert_eq!(game.castling.white_king, false);
+      assert_eq!(game.castling.white_queen, false);
+
+      game.make_move(String::from("e8"), String::from("c8"));
+      assert_eq!(game.board[7][2].unwrap(), Piece::King(Colour::Black));
+      assert_eq!(game.board[7][3].unwrap(), Piece::Rook(Colour::Black));
+      assert_eq!(game.board[7][0].is_none(), true);
+      assert_eq!(game.castling.black_king, false);
+      assert_eq!(game.castling.black_queen, false);
+    }
+
+    #[test]
+    fn castling_through_check() {
+      // the rook on f3 covers f1 so white can only castle queen side
+      let game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/5r2/8/R3K2R w KQkq - 0 1"));
+      let m = game.get_possible_moves(String::from("e1")).unwrap();
+      assert_eq!(m.contains(&String::from("g1")), false);
+      assert_eq!(m.contains(&String::from("c1")), true);
+
+      // can't castle out of check
+      let game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/4r3/8/R3K2R w KQkq - 0 1"));
+      let m = game.get_possible_moves(String::from("e1")).unwrap();
+      assert_eq!(m.contains(&String::from("g1")), false);
+      assert_eq!(m.contains(&String::from("c1")), false);
+
+      // b1 may be attacked when castling queen side, but the path has to be empty
+      let game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/1r6/8/RN2K2R w KQkq - 0 1"));
+      let m = game.get_possible_moves(String::from("e1")).unwrap();
+      assert_eq!(m.contains(&String::from("c1")), false);
+      let game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/1r6/8/R3K2R w KQkq - 0 1"));
+      let m = game.get_possible_moves(String::from("e1")).unwrap();
+      assert_eq!(m.contains(&String::from("c1")), true);
+    }
+
+    #[test]
+    fn castling_rights_are_revoked() {
+      let mut game = Game::new_from_fen(String::from("rn2k1nr/8/8/8/8/8/8/RN2K1NR w KQkq - 0 1"));
+      game.make_move(String::from("a1"), String::from("a8"));
+      assert_eq!(game.castling.white_queen, false);
+      assert_eq!(game.castling.black_queen, false);
+      assert_eq!(game.castling.white_king, true);
+      assert_eq!(game.castling.black_king, true);
+
+      game.make_move(String::from("h8"), String::from("h1"));
+      assert_eq!(game.castling.white_king, false);
+      assert_eq!(game.castling.black_king, false);
+    }
+
+    #[test]
+    fn en_passant() {
+      let mut game = Game::new();
+      game.make_move(String::from("e2"), String::from("e4"));
+      assert_eq!(game.get_en_passant_target(), Some(String::from("e3")));
+      game.make_move(String::from("a7"), String::from("a6"));
+      assert_eq!(game.get_en_passant_target(), None);
+      game.make_move(String::from("e4"), String::from("e5"));
+      game.make_move(String::from("d7"), String::from("d5"));
+      assert_eq!(game.get_en_passant_target(), Some(String::from("d6")));
+      let mut m = game.get_possible_moves(String::from("e5")).unwrap();
+      let mut m2 = vec![String::from("d6"), String::from("e6")];
+      m.sort();
+      m2.sort();
+      assert_eq!(m, m2);
+      game.make_move(String::from("e5"), String::from("d6"));
+      assert_eq!(game.board[5][3].unwrap(), Piece::Pawn(Colour::White));
+      assert_eq!(game.board[4][3].is_none(), true);
+      assert_eq!(game.board[4][4].is_none(), true);
+
+      // the right to capture en passant expires after one move
+      let mut game = Game::new_from_fen(String::from(
+        "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+      ));
+      assert_eq!(game.get_en_passant_target(), Some(String::from("e3")));
+      let mut m = game.get_possible_moves(String::from("d4")).unwrap();
+      let mut m2 = vec![String::from("d3"), String::from("e3")];
+      m.sort();
+      m2.sort();
+      assert_eq!(m, m2);
+      game.make_move(String::from("a7"), String::from("a6"));
+      game.make_move(String::from("a2"), String::from("a3"));
+      let m = game.get_possible_moves(String::from("d4")).unwrap();
+      assert_eq!(m, vec![String::from("d3")]);
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+      // taking on d6 would clear the fifth rank between the rook and the king
+      let game = Game::new_from_fen(String::from("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1"));
+      let m = game.get_possible_moves(String::from("e5")).unwrap();
+      assert_eq!(m, vec![String::from("e6")]);
+    }
+
     #[test]
     fn black_in_check() {
       let mut game = Game::new();
@@ -894,7 +2940,7 @@ mod tests {
       ));
       game.make_move(String::from("d8"), String::from("a5"));
       assert_eq!(game.state, GameState::Checkmate);
-      let mut game = Game::new_from_fen(String::from(
+      let game = Game::new_from_fen(String::from(
         "rnb1kbnr/pp2pppp/8/2pp4/2PPP3/8/PP1q1PPP/RNB1KBNR w KQkq - 0 5",
       ));
       assert_eq!(game.state, GameState::Check);
@@ -920,13 +2966,199 @@ mod tests {
     }
 
     #[test]
-    fn promotion_works() {
-      let mut game = Game::new_from_fen(String::from(
-        "rnbqkbnr/5ppp/8/pNp1N3/3Pp3/8/PPP2PPP/R1BQKB1R w KQkq - 0 7",
+    fn move_clocks() {
+      let mut game = Game::new();
+      assert_eq!(game.get_halfmove_clock(), 0);
+      assert_eq!(game.get_fullmove_number(), 1);
+      game.make_move(String::from("g1"), String::from("f3"));
+      assert_eq!(game.get_halfmove_clock(), 1);
+      assert_eq!(game.get_fullmove_number(), 1);
+      game.make_move(String::from("g8"), String::from("f6"));
+      assert_eq!(game.get_halfmove_clock(), 2);
+      assert_eq!(game.get_fullmove_number(), 2);
+      game.make_move(String::from("e2"), String::from("e4"));
+      assert_eq!(game.get_halfmove_clock(), 0);
+      game.make_move(String::from("b8"), String::from("c6"));
+      assert_eq!(game.get_halfmove_clock(), 1);
+      game.make_move(String::from("f1"), String::from("b5"));
+      game.make_move(String::from("f6"), String::from("e4"));
+      assert_eq!(game.get_halfmove_clock(), 0);
+      assert_eq!(game.get_fullmove_number(), 4);
+
+      let game = Game::new_from_fen(String::from(
+        "8/5k2/3p4/1p1Pp2p/pP2Pp1P/P4P1K/8/8 b - - 99 50",
       ));
-      assert_eq!(game.promote.clone(), (false, String::new()));
-      game.set_promotion(game.promote.1.clone(), 'q');
+      assert_eq!(game.get_halfmove_clock(), 99);
+      assert_eq!(game.get_fullmove_number(), 50);
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+      let mut game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/R7 w - - 98 60"));
+      game.make_move(String::from("a1"), String::from("a2"));
+      assert_eq!(game.state, GameState::InProgress);
+      assert_eq!(game.claim_draw(), false);
+      game.make_move(String::from("e6"), String::from("d6"));
+      assert_eq!(game.state, GameState::FiftyMoveRule);
+      // the draw has to be claimed, playing on is allowed
+      game.make_move(String::from("a2"), String::from("a1"));
+      assert_eq!(game.state, GameState::FiftyMoveRule);
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(game.state, GameState::GameOver);
+      assert_eq!(
+        game
+          .make_move(String::from("d6"), String::from("d5"))
+          .is_none(),
+        true
+      );
+
+      // checkmate on the last move goes before the fifty move rule
+      let mut game = Game::new_from_fen(String::from("6k1/8/6K1/8/8/8/8/R7 w - - 99 60"));
+      game.make_move(String::from("a1"), String::from("a8"));
+      assert_eq!(game.state, GameState::Checkmate);
+    }
 
+    #[test]
+    fn seventy_five_move_rule() {
+      let mut game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/R7 w - - 149 90"));
+      game.make_move(String::from("a1"), String::from("a2"));
+      assert_eq!(game.state, GameState::SeventyFiveMoveRule);
+      assert_eq!(
+        game
+          .make_move(String::from("e6"), String::from("d6"))
+          .is_none(),
+        true
+      );
+    }
+
+    #[test]
+    fn threefold_and_fivefold_repetition() {
+      let mut game = Game::new();
+      assert_eq!(game.get_repetition_count(), 1);
+      let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+      for (from, to) in shuffle.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 2);
+      assert_eq!(game.state, GameState::InProgress);
+      for (from, to) in shuffle.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 3);
+      assert_eq!(game.state, GameState::ThreefoldRepetition);
+
+      // the claim can be skipped, but after five times the game is drawn
+      for _ in 0..2 {
+        for (from, to) in shuffle.iter() {
+          game.make_move(String::from(*from), String::from(*to));
+        }
+      }
+      assert_eq!(game.get_repetition_count(), 5);
+      assert_eq!(game.state, GameState::FivefoldRepetition);
+      assert_eq!(
+        game
+          .make_move(String::from("g1"), String::from("f3"))
+          .is_none(),
+        true
+      );
+
+      let mut game = Game::new();
+      for (from, to) in shuffle.iter().chain(shuffle.iter()) {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(game.state, GameState::GameOver);
+    }
+
+    #[test]
+    fn check_comes_before_claimable_draws() {
+      // the rook checks from a8 every time the position comes up
+      let mut game = Game::new_from_fen(String::from("R6k/8/8/8/8/8/8/7K b - - 0 1"));
+      let shuffle = ["h8h7", "a8a1", "h7h8", "a1a8"];
+      for uci in shuffle.iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(game.can_claim_draw(), false);
+      for uci in shuffle.iter() {
+        game.make_uci_move(uci);
+      }
+      assert_eq!(game.get_repetition_count(), 3);
+      assert_eq!(game.state, GameState::Check);
+      assert_eq!(game.can_claim_draw(), true);
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(game.get_termination(), Some(Termination::Repetition));
+
+      let mut game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/R7 w - - 99 60"));
+      game.make_uci_move("a1a6");
+      assert_eq!(game.state, GameState::Check);
+      assert_eq!(game.can_claim_draw(), true);
+      assert_eq!(game.claim_draw(), true);
+      assert_eq!(game.get_termination(), Some(Termination::FiftyMoveRule));
+    }
+
+    #[test]
+    fn repetition_needs_same_rights() {
+      // the kings walk back, but the castling rights are gone
+      let mut game = Game::new_from_fen(String::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+      let walk = [("e1", "f1"), ("e8", "f8"), ("f1", "e1"), ("f8", "e8")];
+      for (from, to) in walk.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 1);
+      for (from, to) in walk.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 2);
+
+      // an en passant tile that can't be used doesn't count
+      let mut game = Game::new_from_fen(String::from("4k3/8/8/8/4P3/8/8/4K1N1 b - e3 0 1"));
+      let shuffle = [("e8", "d8"), ("g1", "f3"), ("d8", "e8"), ("f3", "g1")];
+      for (from, to) in shuffle.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 2);
+
+      // but one that can be used does
+      let mut game = Game::new_from_fen(String::from("4k3/8/8/8/3pP3/8/8/4K1N1 b - e3 0 1"));
+      for (from, to) in shuffle.iter() {
+        game.make_move(String::from(*from), String::from(*to));
+      }
+      assert_eq!(game.get_repetition_count(), 1);
+    }
+
+    #[test]
+    fn insufficient_material() {
+      let game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/8 w - - 0 1"));
+      assert_eq!(game.state, GameState::InsufficientMaterial);
+      let game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/2B5 w - - 0 1"));
+      assert_eq!(game.state, GameState::InsufficientMaterial);
+      let game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/2n5 w - - 0 1"));
+      assert_eq!(game.state, GameState::InsufficientMaterial);
+      // bishops on the same colour can never mate, on different colours they can
+      let game = Game::new_from_fen(String::from("5b2/8/4k3/8/8/4K3/8/2B5 w - - 0 1"));
+      assert_eq!(game.state, GameState::InsufficientMaterial);
+      let game = Game::new_from_fen(String::from("4b3/8/4k3/8/8/4K3/8/2B5 w - - 0 1"));
+      assert_eq!(game.state, GameState::InProgress);
+      let game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/8/1NN5 w - - 0 1"));
+      assert_eq!(game.state, GameState::InProgress);
+      let game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/P7/8 w - - 0 1"));
+      assert_eq!(game.state, GameState::InProgress);
+
+      // taking the last rook ends the game
+      let mut game = Game::new_from_fen(String::from("8/8/4k3/8/8/4K3/4r3/8 w - - 0 1"));
+      assert_eq!(game.state, GameState::Check);
+      game.make_move(String::from("e3"), String::from("e2"));
+      assert_eq!(game.state, GameState::InsufficientMaterial);
+      assert_eq!(
+        game
+          .make_move(String::from("e6"), String::from("e5"))
+          .is_none(),
+        true
+      );
+    }
+
+    #[test]
+    fn promotion_works() {
       let mut game = Game::new_from_fen(String::from(
         "r1bqkbnr/1P1ppppp/p1p5/8/1P1n4/8/2PPPPPP/RNBQKBNR w KQkq - 0 6",
       ));
@@ -934,32 +3166,18 @@ mod tests {
         game.get_piece_at(String::from("b7")).unwrap(),
         Piece::Pawn(Colour::White)
       );
+      // without a piece given, the pawn becomes a queen right away
       game.make_move(String::from("b7"), String::from("b8"));
-      assert_eq!(game.promote.clone(), (true, String::from("b8")));
-      assert_eq!(
-        game.get_piece_at(String::from("b8")).unwrap(),
-        Piece::Pawn(Colour::White)
-      );
-      game.set_promotion(game.promote.1.clone(), 'q');
-      game.promote = (false, String::new());
       game.print_board();
       assert_eq!(
         game.get_piece_at(String::from("b8")).unwrap(),
         Piece::Queen(Colour::White)
       );
-      assert_eq!(game.promote, (false, String::new()));
 
       let mut game = Game::new_from_fen(String::from(
         "2bqkbnr/1R1ppppp/8/8/3n4/8/p1PPPPPP/1NBQKBNR b Kk - 0 13",
       ));
-      game.make_move(String::from("a2"), String::from("b1"));
-      assert_eq!(game.promote, (true, String::from("b1")));
-      assert_eq!(
-        game.get_piece_at(String::from("b1")).unwrap(),
-        Piece::Pawn(Colour::Black)
-      );
-      game.set_promotion(game.promote.1.clone(), 'r');
-      game.promote = (false, String::new());
+      assert_eq!(game.make_uci_move("a2b1r").is_some(), true);
       assert_eq!(
         game.get_piece_at(String::from("b1")).unwrap(),
         Piece::Rook(Colour::Black)
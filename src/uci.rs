@@ -0,0 +1,200 @@
+use crate::protocol::{
+  self, fixed_time, time_share, Protocol, Signals, MOVES_TO_GO, MOVE_OVERHEAD,
+};
+use crate::{Colour, Game, SearchLimits, SearchResult, ENGINE_NAME};
+use std::io::{self, Write};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const AUTHOR: &str = "alholmbe";
+const MAX_MOVE_OVERHEAD: u64 = 5000;
+
+// speaks the Universal Chess Interface over stdin and stdout until quit
+pub fn uci() {
+  protocol::run(Uci::new, early);
+}
+
+fn early(line: &str, signals: &Signals) -> bool {
+  match line.split_whitespace().next() {
+    Some("stop") => signals.stop.store(true, Ordering::Relaxed),
+    Some("isready") if signals.searching.load(Ordering::Relaxed) => {
+      println!("readyok");
+      return true;
+    }
+    // set here and not when the search starts, so a stop right after go is never missed
+    Some("go") => {
+      signals.stop.store(false, Ordering::Relaxed);
+      signals.searching.store(true, Ordering::Relaxed);
+    }
+    _ => {}
+  }
+  false
+}
+
+pub(crate) struct Uci {
+  game: Game,
+  signals: Arc<Signals>,
+  move_overhead: u64,
+}
+
+impl Protocol for Uci {
+  fn answer(&mut self, name: &str, args: &[&str], out: &mut dyn Write) -> io::Result<()> {
+    match name {
+      "uci" => self.identify(out),
+      "isready" => writeln!(out, "readyok"),
+      "ucinewgame" => {
+        self.game = Game::new();
+        Ok(())
+      }
+      "setoption" => self.set_option(args, out),
+      "position" => match position(args) {
+        Some(game) => {
+          self.game = game;
+          Ok(())
+        }
+        None => writeln!(out, "info string invalid position {}", args.join(" ")),
+      },
+      "go" => self.go(args, out),
+      // stop only matters during a search and the reading thread takes care of it,
+      // and there is nothing to do for debug, register or ponderhit
+      _ => Ok(()),
+    }
+  }
+}
+
+impl Uci {
+  pub(crate) fn new(signals: Arc<Signals>) -> Uci {
+    Uci {
+      game: Game::new(),
+      signals,
+      move_overhead: MOVE_OVERHEAD,
+    }
+  }
+
+  fn identify(&self, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "id name {}", ENGINE_NAME)?;
+    writeln!(out, "id author {}", AUTHOR)?;
+    writeln!(
+      out,
+      "option name Move Overhead type spin default {} min 0 max {}",
+      MOVE_OVERHEAD, MAX_MOVE_OVERHEAD
+    )?;
+    writeln!(out, "uciok")
+  }
+
+  // setoption name <name> value <value>, where the name can have spaces in it
+  fn set_option(&mut self, args: &[&str], out: &mut dyn Write) -> io::Result<()> {
+    let value_at = args.iter().position(|arg| *arg == "value");
+    let name = args[..value_at.unwrap_or(args.len())]
+      .iter()
+      .skip_while(|arg| **arg == "name")
+      .cloned()
+      .collect::<Vec<&str>>()
+      .join(" ");
+    let value = value_at.map(|at| args[at + 1..].join(" "));
+    if !name.eq_ignore_ascii_case("Move Overhead") {
+      return writeln!(out, "info string unknown option {}", name);
+    }
+    match value.and_then(|value| value.parse::<u64>().ok()) {
+      Some(overhead) if overhead <= MAX_MOVE_OVERHEAD => {
+        self.move_overhead = overhead;
+        Ok(())
+      }
+      _ => writeln!(out, "info string invalid value for {}", name),
+    }
+  }
+
+  fn go(&mut self, args: &[&str], out: &mut dyn Write) -> io::Result<()> {
+    let (limits, infinite) = self.limits(args);
+    let result = protocol::search(
+      &self.game,
+      limits,
+      &self.signals.stop,
+      out,
+      &|result, elapsed| Some(info(result, elapsed)),
+    )?;
+    // the best move can't be sent before the GUI says stop, even if the search is over
+    while infinite && !self.signals.stop.load(Ordering::Relaxed) {
+      thread::sleep(Duration::from_millis(1));
+    }
+    self.signals.searching.store(false, Ordering::Relaxed);
+    match result {
+      Some(result) => match result.pv.get(1) {
+        Some(ponder) => writeln!(out, "bestmove {} ponder {}", result.best_move, ponder),
+        None => writeln!(out, "bestmove {}", result.best_move),
+      },
+      None => writeln!(out, "bestmove 0000"),
+    }
+  }
+
+  // go depth, nodes, movetime, wtime, btime, winc, binc, movestogo and infinite,
+  // and whether the search has to wait for stop
+  fn limits(&self, args: &[&str]) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut infinite = false;
+    let mut movetime = None;
+    let (mut time, mut increment, mut moves_to_go) = (None, 0, MOVES_TO_GO);
+    let (time_key, increment_key) = match self.game.turn {
+      Colour::White => ("wtime", "winc"),
+      Colour::Black => ("btime", "binc"),
+    };
+    for (i, arg) in args.iter().enumerate() {
+      let number = args
+        .get(i + 1)
+        .and_then(|number| number.parse::<u64>().ok());
+      match *arg {
+        "infinite" => infinite = true,
+        "depth" => limits.depth = number.map(|depth| depth as u32),
+        "nodes" => limits.nodes = number,
+        "movetime" => movetime = number,
+        "movestogo" => moves_to_go = number.unwrap_or(MOVES_TO_GO).max(1),
+        key if key == time_key => time = number,
+        key if key == increment_key => increment = number.unwrap_or(0),
+        _ => {}
+      }
+    }
+    limits.time = match (movetime, time) {
+      (Some(movetime), _) => Some(fixed_time(movetime, self.move_overhead)),
+      (None, Some(time)) => Some(time_share(time, increment, moves_to_go, self.move_overhead)),
+      (None, None) => None,
+    };
+    (limits, infinite)
+  }
+}
+
+// position startpos|fen <fen> [moves <move>...], None if any of it is wrong
+fn position(args: &[&str]) -> Option<Game> {
+  let moves_at = args
+    .iter()
+    .position(|arg| *arg == "moves")
+    .unwrap_or(args.len());
+  let mut game = match args.first() {
+    Some(&"startpos") if moves_at == 1 => Game::new(),
+    Some(&"fen") => Game::try_from_fen(&args[1..moves_at].join(" ")).ok()?,
+    _ => return None,
+  };
+  for mv in args.iter().skip(moves_at + 1) {
+    game.make_uci_move(mv)?;
+  }
+  Some(game)
+}
+
+fn info(result: &SearchResult, elapsed: Duration) -> String {
+  let score = match result.get_mate_in() {
+    Some(moves) => format!("mate {}", moves),
+    None => format!("cp {}", result.score),
+  };
+  let millis = elapsed.as_millis() as u64;
+  let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+  format!(
+    "info depth {} score {} nodes {} nps {} time {} pv {}",
+    result.depth,
+    score,
+    result.nodes,
+    result.nodes * 1000 / millis.max(1),
+    millis,
+    pv.join(" ")
+  )
+}
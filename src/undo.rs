@@ -0,0 +1,96 @@
+use crate::clock::Press;
+use crate::{Castling, Colour, Game, GameState, Move, Piece, Position, Termination};
+
+// everything a move changes that can't be worked out from the move itself
+#[derive(Debug, Clone)]
+pub(crate) struct Undo {
+  pub(crate) mv: Move,
+  pub(crate) piece: Piece,
+  // where the captured piece stood, which is not the tile moved to for en passant
+  pub(crate) captured: Option<(Position, Piece)>,
+  pub(crate) castling: Castling,
+  pub(crate) en_passant: Option<Position>,
+  pub(crate) halfmove_clock: u32,
+  pub(crate) fullmove_number: u32,
+  pub(crate) state: GameState,
+  pub(crate) draw_offer: Option<Colour>,
+  // what the move did to the clock of the side that moved, if there was one
+  pub(crate) clock_press: Option<Press>,
+}
+
+impl Game {
+  // takes back the last move, returns false if there is none or the game was ended
+  // by resigning, agreeing to a draw or running out of time, which no move can undo
+  pub fn undo(&mut self) -> bool {
+    if let Some((_, Termination::Resignation | Termination::Agreement | Termination::Timeout)) =
+      self.outcome
+    {
+      return false;
+    }
+    let undo = match self.undo_stack.pop() {
+      Some(undo) => undo,
+      None => return false,
+    };
+    self.unmove_pieces(&undo);
+    // the time spent stays spent, but the clock runs for the side to move again
+    if let Some(clock) = &mut self.clock {
+      if let Some(press) = undo.clock_press {
+        clock.take_back(self.turn, press);
+      }
+      clock.switch_to(self.turn);
+    }
+    self.state = undo.state;
+    // the endings that are left follow from the move, or a draw claimed after it
+    self.outcome = None;
+    self.draw_offer = undo.draw_offer;
+    self.history.pop();
+    self.moves.pop();
+    self.redo_stack.push(undo.mv);
+    true
+  }
+
+  // puts the pieces back as they were before the move, see Game::move_pieces
+  pub(crate) fn unmove_pieces(&mut self, undo: &Undo) {
+    let from = undo.mv.from.to_position();
+    let to = undo.mv.to.to_position();
+
+    self.hash ^= self.state_key();
+    self.set_piece(&to, None);
+    self.set_piece(&from, Some(undo.piece));
+    if let Some((position, piece)) = &undo.captured {
+      self.set_piece(position, Some(*piece));
+    }
+    // the rook goes back to its corner after castling
+    if let Piece::King(colour) = undo.piece {
+      if from.1 == 4 && to.1 == 6 {
+        self.set_piece(&Position(to.0, 5), None);
+        self.set_piece(&Position(to.0, 7), Some(Piece::Rook(colour)));
+      } else if from.1 == 4 && to.1 == 2 {
+        self.set_piece(&Position(to.0, 3), None);
+        self.set_piece(&Position(to.0, 0), Some(Piece::Rook(colour)));
+      }
+    }
+
+    self.turn = self.turn.opponent();
+    self.castling = undo.castling.clone();
+    self.en_passant = undo.en_passant.clone();
+    self.halfmove_clock = undo.halfmove_clock;
+    self.fullmove_number = undo.fullmove_number;
+    self.hash ^= self.state_key();
+  }
+
+  // makes the last move taken back again, returns false if there is none
+  pub fn redo(&mut self) -> bool {
+    let mv = match self.redo_stack.pop() {
+      Some(mv) => mv,
+      None => return false,
+    };
+    // making a move forgets the moves taken back, but not when it is one of them
+    let redo_stack = std::mem::take(&mut self.redo_stack);
+    let done = self
+      .make_move_promoting(mv.from.to_string(), mv.to.to_string(), mv.promotion)
+      .is_some();
+    self.redo_stack = redo_stack;
+    done
+  }
+}
@@ -0,0 +1,115 @@
+use crate::{Colour, Game, Piece, Position};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PositionError {
+  MissingKing(Colour),
+  TooManyKings(Colour),
+  // pawns can never stand on the first or last rank
+  PawnOnBackRank(String),
+  // the side that just moved can't have left its own king in check
+  OpponentInCheck,
+  // the castling right, as its FEN letter, doesn't match where the king and rook stand
+  InvalidCastlingRights(char),
+  // no pawn can have made a double step past the en passant tile
+  InvalidEnPassant(String),
+}
+
+impl fmt::Display for PositionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PositionError::MissingKing(colour) => write!(f, "{:?} has no king", colour),
+      PositionError::TooManyKings(colour) => write!(f, "{:?} has more than one king", colour),
+      PositionError::PawnOnBackRank(tile) => write!(f, "there is a pawn on {}", tile),
+      PositionError::OpponentInCheck => write!(f, "the side not to move is in check"),
+      PositionError::InvalidCastlingRights(right) => write!(
+        f,
+        "castling right '{}' doesn't match the king and rook placement",
+        right
+      ),
+      PositionError::InvalidEnPassant(tile) => {
+        write!(f, "no pawn can be captured en passant on {}", tile)
+      }
+    }
+  }
+}
+
+impl std::error::Error for PositionError {}
+
+impl Game {
+  pub(crate) fn validate(&self) -> Result<(), PositionError> {
+    // kings go first since the check test needs them on the board
+    for colour in [Colour::White, Colour::Black].iter() {
+      let kings = self.count_pieces(Piece::King(*colour));
+      if kings == 0 {
+        return Err(PositionError::MissingKing(*colour));
+      } else if kings > 1 {
+        return Err(PositionError::TooManyKings(*colour));
+      }
+    }
+
+    for row in [0, 7].iter() {
+      for col in 0..=7 {
+        if let Some(Piece::Pawn(_)) = self.board[*row][col] {
+          return Err(PositionError::PawnOnBackRank(Game::parse_coordinates(
+            Position(*row, col),
+          )));
+        }
+      }
+    }
+
+    let rights = [
+      ('K', self.castling.white_king, Colour::White, 7),
+      ('Q', self.castling.white_queen, Colour::White, 0),
+      ('k', self.castling.black_king, Colour::Black, 7),
+      ('q', self.castling.black_queen, Colour::Black, 0),
+    ];
+    for (letter, right, colour, rook_col) in rights.iter() {
+      let row = match colour {
+        Colour::White => 0,
+        Colour::Black => 7,
+      };
+      if *right
+        && (self.board[row][4] != Some(Piece::King(*colour))
+          || self.board[row][*rook_col] != Some(Piece::Rook(*colour)))
+      {
+        return Err(PositionError::InvalidCastlingRights(*letter));
+      }
+    }
+
+    if let Some(target) = &self.en_passant {
+      // the pawn moved from behind the target tile to the tile in front of it
+      let (from_row, pawn_row) = match self.turn {
+        Colour::White => (6, 4),
+        Colour::Black => (1, 3),
+      };
+      if self.board[target.0][target.1].is_some()
+        || self.board[from_row][target.1].is_some()
+        || self.board[pawn_row][target.1] != Some(Piece::Pawn(self.turn.opponent()))
+      {
+        return Err(PositionError::InvalidEnPassant(Game::parse_coordinates(
+          target.clone(),
+        )));
+      }
+    }
+
+    let opponent = match self.turn {
+      Colour::White => "black",
+      Colour::Black => "white",
+    };
+    if self.check(String::from(opponent)) {
+      return Err(PositionError::OpponentInCheck);
+    }
+
+    Ok(())
+  }
+
+  fn count_pieces(&self, piece: Piece) -> usize {
+    self
+      .board
+      .iter()
+      .flatten()
+      .filter(|maybe_piece| **maybe_piece == Some(piece))
+      .count()
+  }
+}
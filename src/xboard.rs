@@ -0,0 +1,279 @@
+use crate::protocol::{
+  self, fixed_time, time_share, Protocol, Signals, MOVES_TO_GO, MOVE_OVERHEAD,
+};
+use crate::{Colour, Game, GameResult, Move, SearchLimits, SearchResult};
+use crate::{Termination, ENGINE_NAME};
+use std::io::{self, Write};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+// how mates are written in the thinking output, plus or minus the moves to it
+const MATE_SCORE: i32 = 100_000;
+
+// speaks the XBoard protocol, also known as CECP, over stdin and stdout until quit
+pub fn xboard() {
+  protocol::run(XBoard::new, early);
+}
+
+// set by the reading thread and not when the search starts, so a command that
+// comes in right after the search is asked for is never missed
+fn early(line: &str, signals: &Signals) -> bool {
+  match line.split_whitespace().next() {
+    // move now
+    Some("?") => signals.stop.store(true, Ordering::Relaxed),
+    Some("force") | Some("new") | Some("result") => {
+      signals.abort.store(true, Ordering::Relaxed);
+      signals.stop.store(true, Ordering::Relaxed);
+    }
+    Some("go") | Some("usermove") | Some("playother") => {
+      signals.abort.store(false, Ordering::Relaxed);
+      signals.stop.store(false, Ordering::Relaxed);
+    }
+    _ => {}
+  }
+  false
+}
+
+pub(crate) struct XBoard {
+  game: Game,
+  signals: Arc<Signals>,
+  // the side the engine plays, None in force mode
+  engine: Option<Colour>,
+  // whether to send the thinking output
+  post: bool,
+  depth: Option<u32>,
+  // set by st, the time for every move no matter what is left
+  seconds_per_move: Option<u64>,
+  // set by level, 0 moves per control means the whole game
+  moves_per_control: u64,
+  increment: u64,
+  // the engine's clock in centiseconds, set by level and time
+  time_left: Option<u64>,
+}
+
+impl Protocol for XBoard {
+  fn answer(&mut self, name: &str, args: &[&str], out: &mut dyn Write) -> io::Result<()> {
+    match name {
+      "protover" => self.features(out),
+      "new" => {
+        self.game = Game::new();
+        self.engine = Some(Colour::Black);
+        self.depth = None;
+        Ok(())
+      }
+      "force" | "result" => {
+        self.engine = None;
+        Ok(())
+      }
+      "go" => {
+        self.engine = Some(self.game.turn);
+        self.think(out)
+      }
+      "playother" => {
+        self.engine = Some(self.game.turn.opponent());
+        Ok(())
+      }
+      "usermove" => match args.first() {
+        Some(mv) => self.user_move(mv, out),
+        None => writeln!(out, "Error (no move given): usermove"),
+      },
+      "setboard" => match Game::try_from_fen(&args.join(" ")) {
+        Ok(game) => {
+          self.game = game;
+          Ok(())
+        }
+        Err(_) => writeln!(out, "tellusererror Illegal position"),
+      },
+      "undo" => {
+        self.game.undo();
+        Ok(())
+      }
+      "remove" => {
+        self.game.undo();
+        self.game.undo();
+        Ok(())
+      }
+      "level" => self.set_level(args, out),
+      "st" => match args.first().and_then(|seconds| seconds.parse().ok()) {
+        Some(seconds) => {
+          self.seconds_per_move = Some(seconds);
+          Ok(())
+        }
+        None => writeln!(out, "Error (bad time): st {}", args.join(" ")),
+      },
+      "sd" => match args.first().and_then(|depth| depth.parse().ok()) {
+        Some(depth) => {
+          self.depth = Some(depth);
+          Ok(())
+        }
+        None => writeln!(out, "Error (bad depth): sd {}", args.join(" ")),
+      },
+      "time" => {
+        self.time_left = args.first().and_then(|time| time.parse().ok());
+        Ok(())
+      }
+      "ping" => writeln!(out, "pong {}", args.join(" ")),
+      "post" | "nopost" => {
+        self.post = name == "post";
+        Ok(())
+      }
+      // ? only matters during a search and the reading thread takes care of it,
+      // and the rest give nothing to do for an engine like this one
+      "xboard" | "accepted" | "rejected" | "?" | "otim" | "random" | "hard" | "easy"
+      | "computer" | "name" | "draw" | "hint" | "bk" | "ics" | "white" | "black" => Ok(()),
+      // older interfaces send the moves without usermove
+      _ if Move::from_uci(name).is_some() => self.user_move(name, out),
+      _ => writeln!(out, "Error (unknown command): {}", name),
+    }
+  }
+}
+
+impl XBoard {
+  pub(crate) fn new(signals: Arc<Signals>) -> XBoard {
+    XBoard {
+      game: Game::new(),
+      signals,
+      engine: Some(Colour::Black),
+      post: false,
+      depth: None,
+      seconds_per_move: None,
+      moves_per_control: 0,
+      increment: 0,
+      time_left: None,
+    }
+  }
+
+  fn features(&self, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+      out,
+      "feature ping=1 setboard=1 usermove=1 playother=1 time=1 draw=0 sigint=0 sigterm=0 \
+       reuse=1 analyze=0 colors=0 myname=\"{}\" done=1",
+      ENGINE_NAME
+    )
+  }
+
+  // level <moves per control> <minutes or minutes:seconds> <increment in seconds>
+  fn set_level(&mut self, args: &[&str], out: &mut dyn Write) -> io::Result<()> {
+    let base = args.get(1).and_then(|base| {
+      let mut parts = base.splitn(2, ':');
+      let minutes: u64 = parts.next()?.parse().ok()?;
+      let seconds: u64 = parts.next().unwrap_or("0").parse().ok()?;
+      Some(minutes * 60 + seconds)
+    });
+    let moves = args.first().and_then(|moves| moves.parse().ok());
+    let increment = args.get(2).and_then(|increment| increment.parse().ok());
+    match (moves, base, increment) {
+      (Some(moves), Some(base), Some(increment)) => {
+        self.moves_per_control = moves;
+        self.increment = increment;
+        self.time_left = Some(base * 100);
+        self.seconds_per_move = None;
+        Ok(())
+      }
+      _ => writeln!(out, "Error (bad level): level {}", args.join(" ")),
+    }
+  }
+
+  fn user_move(&mut self, mv: &str, out: &mut dyn Write) -> io::Result<()> {
+    if self.game.make_typed_move(mv).is_none() {
+      return writeln!(out, "Illegal move: {}", mv);
+    }
+    if self.send_result(out)? {
+      return Ok(());
+    }
+    if self.engine == Some(self.game.turn) {
+      return self.think(out);
+    }
+    Ok(())
+  }
+
+  fn think(&mut self, out: &mut dyn Write) -> io::Result<()> {
+    let post = self.post;
+    let result = protocol::search(
+      &self.game,
+      self.limits(),
+      &self.signals.stop,
+      out,
+      &|result, elapsed| Some(thinking(result, elapsed)).filter(|_| post),
+    )?;
+    // force, new or result came in during the search
+    if self.signals.abort.load(Ordering::Relaxed) {
+      return Ok(());
+    }
+    let result = match result {
+      Some(result) => result,
+      None => return Ok(()),
+    };
+    self.game.apply_move(&result.best_move);
+    writeln!(out, "move {}", result.best_move)?;
+    self.send_result(out)?;
+    Ok(())
+  }
+
+  fn limits(&self) -> SearchLimits {
+    let time = match (self.seconds_per_move, self.time_left) {
+      (Some(seconds), _) => Some(fixed_time(seconds * 1000, MOVE_OVERHEAD)),
+      (None, Some(centiseconds)) => {
+        let moves_to_go = match self.moves_per_control {
+          0 => MOVES_TO_GO,
+          moves => moves - (self.game.get_fullmove_number() as u64 - 1) % moves,
+        };
+        let increment = self.increment * 1000;
+        Some(time_share(
+          centiseconds * 10,
+          increment,
+          moves_to_go,
+          MOVE_OVERHEAD,
+        ))
+      }
+      (None, None) => None,
+    };
+    SearchLimits {
+      depth: self.depth,
+      time,
+      nodes: None,
+    }
+  }
+
+  // tells the GUI when the game is over, a draw that can be claimed is claimed,
+  // returns whether it is
+  fn send_result(&mut self, out: &mut dyn Write) -> io::Result<bool> {
+    self.game.claim_draw();
+    let (result, termination) = match (self.game.get_result(), self.game.get_termination()) {
+      (Some(result), Some(termination)) => (result, termination),
+      _ => return Ok(false),
+    };
+    let reason = match termination {
+      Termination::Checkmate if result == GameResult::WhiteWins => "White mates",
+      Termination::Checkmate => "Black mates",
+      Termination::Stalemate => "Stalemate",
+      Termination::Repetition => "Draw by repetition",
+      Termination::FiftyMoveRule => "Draw by fifty move rule",
+      Termination::InsufficientMaterial => "Draw by insufficient material",
+      Termination::Resignation => "Resignation",
+      Termination::Timeout => "Time forfeit",
+      Termination::Agreement => "Draw by agreement",
+    };
+    writeln!(out, "{} {{{}}}", result, reason)?;
+    Ok(true)
+  }
+}
+
+// ply, score, time in centiseconds, nodes and the principal variation
+fn thinking(result: &SearchResult, elapsed: Duration) -> String {
+  let score = match result.get_mate_in() {
+    Some(moves) if moves > 0 => MATE_SCORE + moves,
+    Some(moves) => -MATE_SCORE + moves,
+    None => result.score,
+  };
+  let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+  format!(
+    "{} {} {} {} {}",
+    result.depth,
+    score,
+    elapsed.as_millis() / 10,
+    result.nodes,
+    pv.join(" ")
+  )
+}